@@ -0,0 +1,110 @@
+use std::{
+    future::Future,
+    hash::{Hash, Hasher},
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use event_listener::Event;
+use futures_util::future::{select, Either};
+
+use crate::Error;
+
+/// Cancels the requests it has been passed to via [`crate::How::cancellation`]. Clones share the same
+/// state, so keep one clone around and call [`Self::cancel`] on it, e.g. once the user navigates
+/// away.
+///
+/// Cancelled requests fail with [`Error::Cancelled`] and are never retried. This includes
+/// requests, which are waiting to be retried, and streams which are already being consumed.
+///
+/// ```no_run
+/// use aleph_alpha_client::{CancellationToken, Client, Error, How, TaskCompletion};
+///
+/// async fn complete_unless_cancelled(client: &Client, token: CancellationToken) {
+///     let how = How {
+///         cancellation: Some(token),
+///         ..How::default()
+///     };
+///     let task = TaskCompletion::from_text("An apple a day");
+///     match client.completion(&task, "luminous-base", &how).await {
+///         Ok(output) => println!("{}", output.completion),
+///         Err(Error::Cancelled) => println!("Cancelled by the user"),
+///         Err(error) => eprintln!("{error}"),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<State>);
+
+#[derive(Debug, Default)]
+struct State {
+    cancelled: AtomicBool,
+    event: Event,
+}
+
+impl CancellationToken {
+    /// A token which has not been cancelled yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel all requests using this token, including the ones started in the future.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.event.notify(usize::MAX);
+    }
+
+    /// `true` once [`Self::cancel`] has been called on this token or any of its clones.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Completes once [`Self::cancel`] has been called.
+    pub(crate) async fn cancelled(&self) {
+        loop {
+            // Register interest before checking the flag, so a concurrent call to `cancel` can not
+            // slip in between.
+            let listener = self.0.event.listen();
+            if self.is_cancelled() {
+                return;
+            }
+            listener.await;
+        }
+    }
+}
+
+/// Two tokens are equal if they are clones of each other.
+impl PartialEq for CancellationToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CancellationToken {}
+
+impl Hash for CancellationToken {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).hash(state)
+    }
+}
+
+/// Resolves `future`, unless `cancellation` is cancelled first. In this case the future is dropped
+/// and [`Error::Cancelled`] is returned.
+pub(crate) async fn cancellable<T>(
+    cancellation: Option<&CancellationToken>,
+    future: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    let Some(token) = cancellation else {
+        return future.await;
+    };
+    if token.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+    match select(pin!(future), pin!(token.cancelled())).await {
+        Either::Left((result, _)) => result,
+        Either::Right(((), _)) => Err(Error::Cancelled),
+    }
+}
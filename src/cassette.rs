@@ -0,0 +1,216 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use reqwest::{Request, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::{http::read_body, Error};
+
+/// Records the HTTP interactions of a client to a JSON file and replays them later, like a VCR
+/// cassette. Lets you write fast and deterministic tests of the logic you build on top of this
+/// crate, without setting up a mock server for each of them. Set it with
+/// [`crate::ClientBuilder::with_cassette`]. Requires the `cassette` feature.
+///
+/// Record the cassette once against a real deployment and commit the file. Afterwards replay it in
+/// your tests without any network access:
+///
+/// ```no_run
+/// use aleph_alpha_client::{Cassette, Client, Error, How, TaskCompletion};
+///
+/// async fn completion_from_cassette() -> Result<String, Error> {
+///     // Use `Cassette::record("tests/cassettes/apple.json")` to (re-)record it.
+///     let cassette = Cassette::replay("tests/cassettes/apple.json")?;
+///     let client = Client::builder("https://inference-api.pharia.your-company.com")
+///         .with_api_token("dummy-token")
+///         .with_cassette(cassette)
+///         .build()?;
+///     let task = TaskCompletion::from_text("An apple a day");
+///     let output = client.completion(&task, "luminous-base", &How::default()).await?;
+///     Ok(output.completion)
+/// }
+/// ```
+///
+/// A request is answered with the first recorded interaction, which has not been replayed yet and
+/// matches its method, path, query and body. Headers are neither recorded nor compared, so the
+/// API token never ends up in the cassette. While recording, streamed responses are only handed
+/// out, once they have been received completely. Request compression should be disabled while
+/// recording, as compressed bodies are not human readable.
+///
+/// Clones share the same cassette.
+#[derive(Debug, Clone)]
+pub struct Cassette(Arc<Mutex<Tape>>);
+
+#[derive(Debug)]
+struct Tape {
+    path: PathBuf,
+    mode: Mode,
+    recording: Recording,
+    /// One entry for each interaction of the recording. `true` once it has been replayed.
+    replayed: Vec<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Record,
+    Replay,
+}
+
+/// Content of a cassette file.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Recording {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+struct RecordedRequest {
+    method: String,
+    path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    query: Option<String>,
+    #[serde(default)]
+    body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RecordedResponse {
+    status: u16,
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Cassette {
+    /// Send requests to the API and record them, together with their responses, to `path`. The
+    /// file is overwritten after every interaction, so it is complete even if the test fails
+    /// halfway.
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Self::new(path.into(), Mode::Record, Recording::default())
+    }
+
+    /// Answer requests from the cassette recorded to `path`, without sending them. Requests which
+    /// have not been recorded fail with [`Error::UnrecordedRequest`].
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let content = fs::read(&path).map_err(Error::Cassette)?;
+        let recording = serde_json::from_slice(&content)
+            .map_err(|e| Error::Cassette(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+        Ok(Self::new(path, Mode::Replay, recording))
+    }
+
+    fn new(path: PathBuf, mode: Mode, recording: Recording) -> Self {
+        let replayed = vec![false; recording.interactions.len()];
+        Self(Arc::new(Mutex::new(Tape {
+            path,
+            mode,
+            recording,
+            replayed,
+        })))
+    }
+
+    /// Takes the place of `http.execute(request)`, either recording or replaying the interaction.
+    pub(crate) async fn send(
+        &self,
+        http: &reqwest::Client,
+        request: Request,
+        max_response_bytes: usize,
+    ) -> Result<Response, Error> {
+        let recorded_request = RecordedRequest::from_request(&request);
+        if self.mode() == Mode::Replay {
+            return self.replay_response(&recorded_request);
+        }
+        let response = http.execute(request).await?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| *name != reqwest::header::SET_COOKIE)
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+            .collect();
+        let body = read_body(response, max_response_bytes).await?;
+        let recorded_response = RecordedResponse {
+            status,
+            headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        };
+        let response = recorded_response.to_response()?;
+        self.append(Interaction {
+            request: recorded_request,
+            response: recorded_response,
+        })?;
+        Ok(response)
+    }
+
+    fn mode(&self) -> Mode {
+        self.tape().mode
+    }
+
+    fn replay_response(&self, request: &RecordedRequest) -> Result<Response, Error> {
+        let mut tape = self.tape();
+        let index = tape
+            .recording
+            .interactions
+            .iter()
+            .zip(&tape.replayed)
+            .position(|(interaction, &replayed)| !replayed && interaction.request == *request)
+            .ok_or_else(|| Error::UnrecordedRequest {
+                method: request.method.clone(),
+                path: request.path.clone(),
+            })?;
+        tape.replayed[index] = true;
+        tape.recording.interactions[index].response.to_response()
+    }
+
+    /// Adds the interaction to the recording and writes the entire recording to the file.
+    fn append(&self, interaction: Interaction) -> Result<(), Error> {
+        let mut tape = self.tape();
+        tape.recording.interactions.push(interaction);
+        tape.replayed.push(false);
+        let content = serde_json::to_vec_pretty(&tape.recording)
+            .expect("Serializing a recording must not fail");
+        fs::write(&tape.path, content).map_err(Error::Cassette)
+    }
+
+    fn tape(&self) -> std::sync::MutexGuard<'_, Tape> {
+        // No code panics while holding the lock, so it can not be poisoned.
+        self.0.lock().unwrap()
+    }
+}
+
+impl RecordedRequest {
+    fn from_request(request: &Request) -> Self {
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+            .unwrap_or_default();
+        Self {
+            method: request.method().to_string(),
+            path: request.url().path().to_owned(),
+            query: request.url().query().map(str::to_owned),
+            body,
+        }
+    }
+}
+
+impl RecordedResponse {
+    /// Fails if status or headers are invalid, e.g. because the cassette has been edited by hand.
+    fn to_response(&self) -> Result<Response, Error> {
+        let mut builder = http::Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        let response = builder
+            .body(self.body.clone())
+            .map_err(|e| Error::Cassette(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+        Ok(response.into())
+    }
+}
@@ -1,17 +1,110 @@
 use core::str;
 use std::borrow::Cow;
 
-use serde::{Deserialize, Serialize};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use futures_util::{Stream, StreamExt};
+use serde::{de::DeserializeOwned, ser::SerializeMap, Deserialize, Serialize, Serializer};
+use tokenizers::Tokenizer;
 
 use crate::{
+    completion::{is_none_or_zero, WithSamplingDefaults},
+    extra::WithExtra,
     logprobs::{Logprob, Logprobs},
-    Stopping, StreamTask, Task,
+    validation::check_penalty,
+    Client, Error, How, ModelName, Priority, Sampling, Stopping, StreamTask, Task,
+    TruncationStrategy, ValidationWarning,
 };
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+/// Upper bound for the tokens a chat template adds around each message, e.g. markers for the
+/// start and end of the message and the header containing the role.
+const TEMPLATE_TOKENS_PER_MESSAGE: u32 = 4;
+
+/// Upper bound for the tokens a chat template adds once per conversation, e.g. a begin of text
+/// marker and the header of the answer the model is about to generate.
+const TEMPLATE_TOKENS_PER_CHAT: u32 = 4;
+
+/// Tokens of role and content of the message, plus the ones its chat template adds.
+fn estimated_message_tokens(tokenizer: &Tokenizer, message: &Message<'_>) -> Result<u32, Error> {
+    let count = |text: &str| {
+        tokenizer
+            .encode(text, false)
+            .map(|encoding| encoding.len() as u32)
+            .map_err(|e| Error::InvalidTokenizer {
+                deserialization_error: e.to_string(),
+            })
+    };
+    Ok(count(&message.role)? + count(&message.content)? + TEMPLATE_TOKENS_PER_MESSAGE)
+}
+
+/// The well known roles of a participant in a chat conversation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+impl Role {
+    /// Representation of the role in a [`Message`].
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+
+    /// Inverse of [`Self::as_str`]. `None` if `role` is not one of the well known roles.
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "system" => Some(Role::System),
+            "user" => Some(Role::User),
+            "assistant" => Some(Role::Assistant),
+            _ => None,
+        }
+    }
+}
+
+impl From<Role> for Cow<'_, str> {
+    fn from(role: Role) -> Self {
+        Cow::Borrowed(role.as_str())
+    }
+}
+
+/// A message which owns its content. Convenient if you build conversations from owned strings and
+/// need to pass them across await points. Prefer borrowing [`Message`]s in hot loops.
+pub type OwnedMessage = Message<'static>;
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
 pub struct Message<'a> {
     pub role: Cow<'a, str>,
     pub content: Cow<'a, str>,
+    /// Images shown to the model as part of the message, e.g. created with
+    /// [`Message::image_with_text`]. If there are any, the content is sent as a list of parts:
+    /// the text first, followed by the images. Only supported by multimodal models. Never set for
+    /// messages received from the model.
+    #[serde(skip)]
+    pub images: Vec<ChatImage>,
+}
+
+/// An image attached to a chat [`Message`]. Sent inline as a `data` URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatImage {
+    /// Media type of the image, e.g. `image/png`.
+    pub mime: String,
+    /// The encoded image (e.g. the bytes of a PNG file) in base64.
+    pub base64: String,
+}
+
+impl ChatImage {
+    /// An image from its encoded bytes, e.g. the contents of a PNG or JPEG file, and their media
+    /// type, e.g. `image/png`.
+    pub fn from_bytes(bytes: &[u8], mime: impl Into<String>) -> Self {
+        Self {
+            mime: mime.into(),
+            base64: BASE64_STANDARD.encode(bytes),
+        }
+    }
 }
 
 impl<'a> Message<'a> {
@@ -19,8 +112,37 @@ impl<'a> Message<'a> {
         Self {
             role: role.into(),
             content: content.into(),
+            images: Vec::new(),
         }
     }
+
+    /// A user message showing an image to the model together with text, e.g. a question about
+    /// the image or a hint what to look for. `bytes` is the encoded image, e.g. the contents of a
+    /// PNG file, and `mime` its media type, e.g. `image/png`.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, Error, How, Message, TaskChat};
+    ///
+    /// async fn describe(client: &Client, png: &[u8]) -> Result<String, Error> {
+    ///     let message = Message::image_with_text(png, "image/png", "Describe the chart.");
+    ///     let task = TaskChat::with_message(message);
+    ///     let output = client.chat(&task, "pixtral-12b", &How::default()).await?;
+    ///     Ok(output.message.content.into_owned())
+    /// }
+    /// ```
+    pub fn image_with_text(
+        bytes: &[u8],
+        mime: impl Into<String>,
+        text: impl Into<Cow<'a, str>>,
+    ) -> Self {
+        Self::user(text).with_image(ChatImage::from_bytes(bytes, mime))
+    }
+
+    /// Attach an image to the message. See [`Self::images`].
+    pub fn with_image(mut self, image: ChatImage) -> Self {
+        self.images.push(image);
+        self
+    }
     pub fn user(content: impl Into<Cow<'a, str>>) -> Self {
         Self::new("user", content)
     }
@@ -30,8 +152,79 @@ impl<'a> Message<'a> {
     pub fn system(content: impl Into<Cow<'a, str>>) -> Self {
         Self::new("system", content)
     }
+
+    /// Removes control characters from the content, which some deployments reject. Stripped are
+    /// all characters of the Unicode category `Cc` (e.g. `\u{0}` or `\u{1b}`), except tab, line
+    /// feed and carriage return. Everything else is kept as is. Invalid UTF-8 and lone surrogates
+    /// need no treatment, since they can not be part of a Rust string in the first place. Content
+    /// without control characters is not copied.
+    pub fn sanitized(self) -> Self {
+        fn is_problematic(c: char) -> bool {
+            c.is_control() && !matches!(c, '\t' | '\n' | '\r')
+        }
+        let content = if self.content.contains(is_problematic) {
+            Cow::Owned(
+                self.content
+                    .chars()
+                    .filter(|&c| !is_problematic(c))
+                    .collect(),
+            )
+        } else {
+            self.content
+        };
+        Self { content, ..self }
+    }
+}
+
+/// Sends the content as a plain string, or as a list of parts if the message contains images. The
+/// latter follows the OpenAI convention for multimodal content.
+impl Serialize for Message<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(tag = "type", rename_all = "snake_case")]
+        enum Part<'a> {
+            Text { text: &'a str },
+            ImageUrl { image_url: ImageUrl },
+        }
+        #[derive(Serialize)]
+        struct ImageUrl {
+            url: String,
+        }
+
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("role", &self.role)?;
+        if self.images.is_empty() {
+            map.serialize_entry("content", &self.content)?;
+        } else {
+            let text = (!self.content.is_empty()).then(|| Part::Text {
+                text: &self.content,
+            });
+            let images = self.images.iter().map(|image| Part::ImageUrl {
+                image_url: ImageUrl {
+                    url: format!("data:{};base64,{}", image.mime, image.base64),
+                },
+            });
+            let parts: Vec<Part> = text.into_iter().chain(images).collect();
+            map.serialize_entry("content", &parts)?;
+        }
+        map.end()
+    }
 }
 
+/// A user message with the given content.
+impl From<String> for OwnedMessage {
+    fn from(content: String) -> Self {
+        Message::user(content)
+    }
+}
+
+impl From<(Role, String)> for OwnedMessage {
+    fn from((role, content): (Role, String)) -> Self {
+        Message::new(role, content)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskChat<'a> {
     /// The list of messages comprising the conversation so far.
     pub messages: Vec<Message<'a>>,
@@ -42,6 +235,116 @@ pub struct TaskChat<'a> {
     /// Use this to control the logarithmic probabilities you want to have returned. This is useful
     /// to figure out how likely it had been that this specific token had been sampled.
     pub logprobs: Logprobs,
+    /// JSON schema the output of the model is forced to conform to, using constrained decoding on
+    /// the server side. This is stronger than asking the model for JSON in the prompt. Send under
+    /// the `guided_json` parameter, which is supported by models served by vLLM based workers.
+    /// Other deployments may ignore or reject it.
+    pub guided_json: Option<serde_json::Value>,
+    /// Documents the model should ground its answer in. Sent as the `documents` parameter, which
+    /// only some deployments support. Omitted from the request if empty. Citations of these
+    /// documents are returned in [`ChatOutput::citations`].
+    pub documents: Vec<Document<'a>>,
+    /// Additional parameters merged into the request body, e.g. toggles specific to your
+    /// deployment which this crate does not know about. Parameters set via the typed fields take
+    /// precedence, i.e. an entry clashing with a parameter the typed fields already send is
+    /// ignored. See [`Self::with_extra`].
+    pub extra: serde_json::Map<String, serde_json::Value>,
+    /// Return the tokens the answer consists of, as strings in [`ChatOutput::tokens`] and as ids in
+    /// [`ChatOutput::token_ids`]. Sent as the `tokens` parameter, which is not supported by every
+    /// deployment. Omitted from the request if `false`.
+    pub tokens: bool,
+    /// How urgently the answer should be generated. Only supported by some deployments, see
+    /// [`Priority`]. `None` omits the parameter.
+    pub priority: Option<Priority>,
+    /// Treat the last message, which must be from the assistant, as the beginning of the answer
+    /// (prefill), rather than as a completed turn. The model continues its content, which steers
+    /// the format of the answer, e.g. a prefill of `{` forces JSON. See [`Self::with_prefill`].
+    ///
+    /// Sent as the `continue_final_message` parameter, together with `add_generation_prompt` set
+    /// to `false`, which are supported by models served by vLLM based workers. Other deployments
+    /// may ignore or reject them.
+    ///
+    /// The content of [`ChatOutput::message`] starts with the prefill, followed by the generated
+    /// continuation, so it is the complete answer. Streamed deltas contain only the
+    /// continuation.
+    pub continue_final_message: bool,
+}
+
+/// A document attached to a chat via [`TaskChat::with_documents`], for answers grounded in (and
+/// citing) its text.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Document<'a> {
+    /// The content the model may cite from.
+    pub text: Cow<'a, str>,
+    /// Identifies the document in [`Citation::document_id`]. If unset, citations refer to the
+    /// document by its position only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Cow<'a, str>>,
+    /// Arbitrary metadata, e.g. title or source URL, passed along to the model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+impl<'a> Document<'a> {
+    /// A document with the given text, but without id or metadata.
+    pub fn new(text: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            text: text.into(),
+            id: None,
+            metadata: None,
+        }
+    }
+
+    /// Sets the id the document is referred to by in citations.
+    pub fn with_id(mut self, id: impl Into<Cow<'a, str>>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Attaches metadata to the document.
+    pub fn with_metadata(mut self, metadata: serde_json::Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+}
+
+/// A reference from the answer to one of the [`TaskChat::documents`]. Parsed from the `citations`
+/// array of the response choice, which is expected to look like this:
+///
+/// ```json
+/// "citations": [
+///     { "document_index": 0, "document_id": "handbook", "text": "quoted passage" }
+/// ]
+/// ```
+///
+/// Only `document_index` is required.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Citation {
+    /// Position of the cited document in [`TaskChat::documents`].
+    pub document_index: usize,
+    /// [`Document::id`] of the cited document, if it had one.
+    #[serde(default)]
+    pub document_id: Option<String>,
+    /// The passage of the document the answer relies on, if reported.
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+/// A conversation without any messages. All optional TaskChat attributes are left unset. Useful
+/// in combination with struct update syntax, e.g.
+/// `TaskChat { messages, ..TaskChat::default() }`.
+impl Default for TaskChat<'_> {
+    fn default() -> Self {
+        Self::with_messages(Vec::new())
+    }
+}
+
+/// Collects the messages into a conversation, e.g. after filtering a stored history. All optional
+/// TaskChat attributes are left unset.
+impl<'a> FromIterator<Message<'a>> for TaskChat<'a> {
+    fn from_iter<I: IntoIterator<Item = Message<'a>>>(messages: I) -> Self {
+        Self::with_messages(messages.into_iter().collect())
+    }
 }
 
 impl<'a> TaskChat<'a> {
@@ -59,6 +362,12 @@ impl<'a> TaskChat<'a> {
             sampling: ChatSampling::default(),
             stopping: Stopping::default(),
             logprobs: Logprobs::No,
+            guided_json: None,
+            documents: Vec::new(),
+            extra: serde_json::Map::new(),
+            tokens: false,
+            priority: None,
+            continue_final_message: false,
         }
     }
 
@@ -68,23 +377,240 @@ impl<'a> TaskChat<'a> {
         self
     }
 
+    /// Sets the system prompt of the conversation. Replaces the content of the first message, if it
+    /// is a system message already. Otherwise the system message is inserted at the start of the
+    /// conversation.
+    pub fn with_system(mut self, content: impl Into<Cow<'a, str>>) -> Self {
+        match self.messages.first_mut() {
+            Some(first) if first.role == Role::System.as_str() => first.content = content.into(),
+            _ => self.messages.insert(0, Message::system(content)),
+        }
+        self
+    }
+
+    /// Check the task for mistakes before sending it. Parameters the API would reject are reported
+    /// as [`Error::InvalidParameter`]. Combinations which are accepted by the API, but likely not
+    /// what you intended, are returned as warnings. This is optional and not invoked by the client
+    /// when executing the task.
+    ///
+    /// Penalties must be within `[-2, 2]`. If your deployment accepts a wider range, simply do not
+    /// call this method.
+    pub fn validate(&self) -> Result<Vec<ValidationWarning>, Error> {
+        check_penalty("frequency_penalty", self.sampling.frequency_penalty)?;
+        check_penalty("presence_penalty", self.sampling.presence_penalty)?;
+        if self.continue_final_message
+            && self
+                .messages
+                .last()
+                .is_none_or(|message| message.role != Role::Assistant.as_str())
+        {
+            return Err(Error::InvalidParameter {
+                field: "continue_final_message".to_owned(),
+                message: "requires the last message to be from the assistant".to_owned(),
+            });
+        }
+        let mut warnings = Vec::new();
+        let num_system_messages = self
+            .messages
+            .iter()
+            .filter(|message| message.role == Role::System.as_str())
+            .count();
+        if num_system_messages > 1 {
+            warnings.push(ValidationWarning::new(
+                "messages",
+                format!(
+                    "contains {num_system_messages} system messages, some models only honor the \
+                    first one"
+                ),
+            ));
+        }
+        Ok(warnings)
+    }
+
+    /// Appends the message the model answered with to the conversation, so it is part of the
+    /// context for the next turn. If the answer continued a prefill, the prefill is replaced by the
+    /// complete answer and [`Self::continue_final_message`] is reset.
+    pub fn append_response(mut self, output: &ChatOutput) -> Self {
+        if self.continue_final_message {
+            // The content of the answer already starts with the prefill.
+            self.messages.pop();
+            self.continue_final_message = false;
+        }
+        self.messages.push(Message::new(
+            output.message.role.clone(),
+            output.message.content.clone(),
+        ));
+        self
+    }
+
     /// Sets the maximum token attribute of this TaskChat.
     pub fn with_maximum_tokens(mut self, maximum_tokens: u32) -> Self {
         self.stopping.maximum_tokens = Some(maximum_tokens);
         self
     }
+
+    /// Let the model generate as many tokens as fit into its context, after the conversation so
+    /// far. Looks up the context size and the tokenizer of `model`, so this sends two requests.
+    /// If the API limits the completion any further, this limit is used instead.
+    ///
+    /// The messages are tokenized locally. Since the exact chat template of the model is not
+    /// known, a few tokens per message are reserved for it, so the result may be slightly lower
+    /// than the actual remaining budget, but never higher. Fails with [`Error::PromptTooLong`] if
+    /// the conversation already fills the context.
+    pub async fn with_max_tokens_auto(
+        mut self,
+        client: &Client,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<Self, Error> {
+        let model = model.into();
+        let metadata = client.model_metadata(model.clone(), how).await?;
+        let tokenizer = client
+            .tokenizer_by_model(model, how.api_token.clone())
+            .await?;
+        let prompt_tokens = self.estimated_prompt_tokens(&tokenizer)?;
+        let max_context_size = metadata.max_context_size;
+        if prompt_tokens >= max_context_size {
+            return Err(Error::PromptTooLong {
+                prompt_tokens: Some(prompt_tokens),
+                max_context_size: Some(max_context_size),
+            });
+        }
+        let remaining = max_context_size - prompt_tokens;
+        let maximum_tokens = metadata
+            .maximum_completion_tokens
+            .map_or(remaining, |limit| limit.min(remaining));
+        self.stopping.maximum_tokens = Some(maximum_tokens);
+        Ok(self)
+    }
+
+    /// Number of tokens the conversation occupies in the context of the model, e.g. to decide
+    /// whether to trim it before sending. Pass the tokenizer of the model, see
+    /// [`Client::tokenizer_by_model`].
+    ///
+    /// The chat template of the model is applied on the server and not known to the client.
+    /// Instead, the role and the content of every message are tokenized, and a fixed number of
+    /// tokens is added per message and per conversation for the markers a chat template renders
+    /// around them. These are chosen generously, so the result is a close upper bound, rather than
+    /// the exact count. Every role is treated the same way, so system, user and assistant messages
+    /// of the same length are estimated alike.
+    pub fn estimated_prompt_tokens(&self, tokenizer: &Tokenizer) -> Result<u32, Error> {
+        let mut total = TEMPLATE_TOKENS_PER_CHAT;
+        for message in &self.messages {
+            total += estimated_message_tokens(tokenizer, message)?;
+        }
+        Ok(total)
+    }
+
+    /// Drops messages according to `strategy`, until [`Self::estimated_prompt_tokens`] is at most
+    /// `max_prompt_tokens`. Pass the context size of the model minus the tokens you want to
+    /// leave for the answer. Conversations which already fit are returned unchanged.
+    ///
+    /// Fails with [`Error::PromptTooLong`] for [`TruncationStrategy::Error`], or if the
+    /// conversation is still too long after dropping every message the strategy allows to drop.
+    /// In this case `max_context_size` of the error is `max_prompt_tokens`.
+    pub fn truncate_to(
+        mut self,
+        tokenizer: &Tokenizer,
+        max_prompt_tokens: u32,
+        strategy: TruncationStrategy,
+    ) -> Result<Self, Error> {
+        if strategy == TruncationStrategy::None {
+            return Ok(self);
+        }
+        let mut message_tokens = self
+            .messages
+            .iter()
+            .map(|message| estimated_message_tokens(tokenizer, message))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut total = TEMPLATE_TOKENS_PER_CHAT + message_tokens.iter().sum::<u32>();
+        while total > max_prompt_tokens {
+            let Some(index) = strategy.next_to_drop(&self.messages) else {
+                return Err(Error::PromptTooLong {
+                    prompt_tokens: Some(total),
+                    max_context_size: Some(max_prompt_tokens),
+                });
+            };
+            self.messages.remove(index);
+            total -= message_tokens.remove(index);
+        }
+        Ok(self)
+    }
+
+    /// Request the log probabilities of the sampled tokens, and with [`Logprobs::Top`] also of the
+    /// most likely alternatives at each position. They are returned in [`ChatOutput::logprobs`].
+    pub fn with_logprobs(mut self, logprobs: Logprobs) -> Self {
+        self.logprobs = logprobs;
+        self
+    }
+
+    /// Force the answer to conform to the given JSON schema. See [`Self::guided_json`].
+    pub fn with_json_schema(mut self, schema: serde_json::Value) -> Self {
+        self.guided_json = Some(schema);
+        self
+    }
+
+    /// Configure the chat for reproducible answers, e.g. for evaluations. Sets the temperature to 0
+    /// and disables `top_p`. Penalties are kept. Any of these can be changed again afterwards.
+    /// `top_p` is set to `0` rather than `None`, so the default sampling of the client does not
+    /// fill it in again.
+    ///
+    /// This is the correct configuration on the client side, yet the server does not guarantee
+    /// identical answers for identical requests, e.g. due to batching on the GPU. A seed is not
+    /// sent, since the API does not accept one.
+    pub fn deterministic(mut self) -> Self {
+        self.sampling.temperature = Some(0.0);
+        self.sampling.top_p = Some(0.0);
+        self
+    }
+
+    /// Send an additional parameter along with the typed ones. See [`Self::extra`].
+    pub fn with_extra(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.extra.insert(key.into(), value);
+        self
+    }
+
+    /// Return the individual tokens of the answer. See [`Self::tokens`].
+    pub fn with_tokens(mut self) -> Self {
+        self.tokens = true;
+        self
+    }
+
+    /// Ground the answer in the given documents. See [`Self::documents`].
+    pub fn with_documents(mut self, documents: Vec<Document<'a>>) -> Self {
+        self.documents = documents;
+        self
+    }
+
+    /// Start the answer of the model with `prefill`, e.g. `{` to force a JSON object. Appends an
+    /// assistant message and lets the model continue it. See [`Self::continue_final_message`].
+    pub fn with_prefill(mut self, prefill: impl Into<Cow<'a, str>>) -> Self {
+        self.messages.push(Message::assistant(prefill));
+        self.continue_final_message = true;
+        self
+    }
+
+    /// Set how urgently the answer should be generated. See [`Self::priority`].
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
 }
 
 /// Sampling controls how the tokens ("words") are selected for the completion. This is different
 /// from [`crate::Sampling`], because it does **not** supprot the `top_k` parameter.
+#[derive(Debug, Clone, PartialEq)]
 pub struct ChatSampling {
     /// A temperature encourages the model to produce less probable outputs ("be more creative").
-    /// Values are expected to be between 0 and 1. Try high values for a more random ("creative")
-    /// response.
+    /// The logits are divided by the temperature before sampling, so `1` leaves the distribution
+    /// untouched and values close to `0` approach always choosing the most likely token. Values
+    /// between 0 and 2 are accepted. Try high values for a more random ("creative") response.
+    /// Values the server does not accept are reported as [`crate::Error::InvalidParameter`].
     pub temperature: Option<f64>,
     /// Introduces random sampling for generated tokens by randomly selecting the next token from
-    /// the k most likely options. A value larger than 1 encourages the model to be more creative.
-    /// Set to 0 to get the same behaviour as `None`.
+    /// the smallest possible set of tokens whose cumulative probability exceeds the probability
+    /// top_p. Set to 0 to get the same behaviour as `None`, i.e. the parameter is omitted from the
+    /// request, just like for completions (see [`crate::Sampling::top_p`]).
     pub top_p: Option<f64>,
     /// When specified, this number will decrease (or increase) the likelihood of repeating tokens
     /// that were mentioned prior in the completion. The penalty is cumulative. The more a token
@@ -113,6 +639,32 @@ impl ChatSampling {
         frequency_penalty: None,
         presence_penalty: None,
     };
+
+    /// Favours variety over precision. A good starting point for brainstorming or creative
+    /// writing.
+    pub const CREATIVE: Self = ChatSampling {
+        temperature: Some(0.8),
+        top_p: None,
+        frequency_penalty: None,
+        presence_penalty: None,
+    };
+
+    /// Some variety, while cutting off unlikely tokens. A good starting point for conversations.
+    pub const BALANCED: Self = ChatSampling {
+        temperature: Some(0.5),
+        top_p: Some(0.9),
+        frequency_penalty: None,
+        presence_penalty: None,
+    };
+
+    /// Sticks closely to the most likely tokens, with a little randomness. A good starting point
+    /// for question answering or extraction.
+    pub const PRECISE: Self = ChatSampling {
+        temperature: Some(0.2),
+        top_p: None,
+        frequency_penalty: None,
+        presence_penalty: None,
+    };
 }
 
 impl Default for ChatSampling {
@@ -121,12 +673,50 @@ impl Default for ChatSampling {
     }
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
 }
 
+/// Why the model stopped generating tokens. Parsed from the `finish_reason` reported by chat
+/// responses, e.g. `FinishReason::from(output.finish_reason.as_str())`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FinishReason {
+    /// The model reached a natural end of its answer or generated a stop sequence.
+    Stop,
+    /// The maximum number of tokens has been generated.
+    Length,
+    /// The answer has been withheld or cut short by a content filter. The content of the message
+    /// may be empty or redacted.
+    ContentFilter,
+    /// Any reason not known to this client.
+    Other(String),
+}
+
+impl FinishReason {
+    /// Representation of the reason in the API.
+    pub fn as_str(&self) -> &str {
+        match self {
+            FinishReason::Stop => "stop",
+            FinishReason::Length => "length",
+            FinishReason::ContentFilter => "content_filter",
+            FinishReason::Other(reason) => reason,
+        }
+    }
+}
+
+impl From<&str> for FinishReason {
+    fn from(reason: &str) -> Self {
+        match reason {
+            "stop" => FinishReason::Stop,
+            "length" => FinishReason::Length,
+            "content_filter" => FinishReason::ContentFilter,
+            other => FinishReason::Other(other.to_owned()),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct ChatOutput {
     pub message: Message<'static>,
@@ -135,6 +725,19 @@ pub struct ChatOutput {
     /// been set to [`crate::Logprobs::Sampled`] or [`crate::Logprobs::Top`].
     pub logprobs: Vec<Distribution>,
     pub usage: Usage,
+    /// The model which actually served the request, as echoed by the server. May differ from the
+    /// requested model, e.g. if you address models by an alias. `None` if the server did not
+    /// report it and for outputs assembled from a stream.
+    pub model: Option<String>,
+    /// References to the [`TaskChat::documents`] the answer relies on. Empty if no documents have
+    /// been attached, or the server does not report citations.
+    pub citations: Vec<Citation>,
+    /// The tokens of the answer as strings, if requested with [`TaskChat::with_tokens`]. `None` if
+    /// the server ignored the request.
+    pub tokens: Option<Vec<String>>,
+    /// The ids of the tokens of the answer, if requested with [`TaskChat::with_tokens`]. `None`
+    /// if the server ignored the request.
+    pub token_ids: Option<Vec<u32>>,
 }
 
 impl ChatOutput {
@@ -149,7 +752,53 @@ impl ChatOutput {
             finish_reason,
             logprobs,
             usage,
+            model: None,
+            citations: Vec::new(),
+            tokens: None,
+            token_ids: None,
+        }
+    }
+
+    /// Folds the events of a chat stream, as returned by [`crate::Client::stream_chat`] for `task`,
+    /// into the complete answer. The message keeps the role announced by the first delta. If the
+    /// task continues a prefill, the content starts with it, so the output can be passed to
+    /// [`TaskChat::append_response`]. Fails if the stream reports an error or ends before the
+    /// finish reason and token usage have been received. Note that `logprobs` are always empty,
+    /// since they are not part of the stream.
+    pub async fn from_stream(
+        task: &TaskChat<'_>,
+        events: impl Stream<Item = Result<StreamChatEvent, Error>>,
+    ) -> Result<Self, Error> {
+        let mut events = std::pin::pin!(events);
+        let mut collector = ChatStreamCollector::new(task);
+        while let Some(event) = events.next().await {
+            collector.push(event?);
         }
+        collector.finish()
+    }
+
+    /// Number of tokens the model generated, as counted by the server. Counterpart of
+    /// [`crate::CompletionOutput::generated_tokens`]. Chat responses always report their usage, so
+    /// this is `usage.completion_tokens` and never `None`.
+    pub fn generated_tokens(&self) -> Option<u32> {
+        Some(self.usage.completion_tokens)
+    }
+
+    /// `true` if the answer has been withheld or cut short by a content filter. In this case the
+    /// content of the message may be empty or redacted and should not be treated like a regular
+    /// answer.
+    pub fn was_filtered(&self) -> bool {
+        FinishReason::from(self.finish_reason.as_str()) == FinishReason::ContentFilter
+    }
+
+    /// Deserialize the content of the message, e.g. if you asked the model to answer in JSON (see
+    /// [`TaskChat::with_json_schema`]). In case the content is not valid JSON for `T`, the returned
+    /// [`Error::ContentParse`] contains the raw content for debugging.
+    pub fn parse_json<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        serde_json::from_str(&self.message.content).map_err(|source| Error::ContentParse {
+            content: self.message.content.to_string(),
+            source,
+        })
     }
 }
 
@@ -158,6 +807,14 @@ pub struct ResponseChoice {
     pub message: Message<'static>,
     pub finish_reason: String,
     pub logprobs: Option<LogprobContent>,
+    #[serde(default)]
+    pub citations: Vec<Citation>,
+    /// Tokens of the message, if requested.
+    #[serde(default)]
+    pub tokens: Option<Vec<String>>,
+    /// Ids of the tokens of the message, if requested.
+    #[serde(default)]
+    pub token_ids: Option<Vec<u32>>,
 }
 
 #[derive(Deserialize, Debug, PartialEq, Default)]
@@ -180,6 +837,8 @@ pub struct Distribution {
 pub struct ChatResponse {
     choices: Vec<ResponseChoice>,
     usage: Usage,
+    #[serde(default)]
+    model: Option<String>,
 }
 
 /// Additional options to affect the streaming behavior.
@@ -191,6 +850,31 @@ struct StreamOptions {
     include_usage: bool,
 }
 
+/// Serialization of stop sequences in the body of a chat request.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(untagged)]
+enum Stop<'a> {
+    One(&'a str),
+    Many(&'a [&'a str]),
+}
+
+impl<'a> Stop<'a> {
+    /// `None` if there are no stop sequences, so the parameter can be omitted.
+    fn new(stop_sequences: &'a [&'a str]) -> Option<Self> {
+        match stop_sequences {
+            [] => None,
+            [one] => Some(Stop::One(one)),
+            many => Some(Stop::Many(many)),
+        }
+    }
+}
+
+/// Body send to the API on the POST `/chat/completions` route.
+///
+/// Fields are serialized in the order they are declared, so the body of a given task is always the
+/// same string. Tests comparing request bodies verbatim rely on this, so do not reorder fields and
+/// append new ones at the end. The only exception are tasks with [`TaskChat::extra`] parameters,
+/// whose bodies are merged into an object with keys in alphabetical order.
 #[derive(Serialize)]
 struct ChatBody<'a> {
     /// Name of the model tasked with completing the prompt. E.g. `luminous-base"`.
@@ -200,8 +884,10 @@ struct ChatBody<'a> {
     /// Limits the number of tokens, which are generated for the completion.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<u32>,
-    #[serde(skip_serializing_if = "<[_]>::is_empty")]
-    pub stop: &'a [&'a str],
+    /// Stop sequences. Sent as a bare string if there is exactly one, as some OpenAI compatible
+    /// deployments only accept this form, and as an array otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Stop<'a>>,
     /// Controls the randomness of the model. Lower values will make the model more deterministic and higher values will make it more random.
     /// Mathematically, the temperature is used to divide the logits before sampling. A temperature of 0 will always return the most likely token.
     /// When no value is provided, the default value of 1 will be used.
@@ -209,7 +895,7 @@ struct ChatBody<'a> {
     pub temperature: Option<f64>,
     /// "nucleus" parameter to dynamically adjust the number of choices for each predicted token based on the cumulative probabilities. It specifies a probability threshold, below which all less likely tokens are filtered out.
     /// When no value is provided, the default value of 1 will be used.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "is_none_or_zero")]
     pub top_p: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub frequency_penalty: Option<f64>,
@@ -224,6 +910,24 @@ struct ChatBody<'a> {
     pub top_logprobs: Option<u8>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream_options: Option<StreamOptions>,
+    /// JSON schema the answer is forced to conform to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guided_json: Option<&'a serde_json::Value>,
+    /// Documents the answer is grounded in.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub documents: &'a [Document<'a>],
+    /// Return the tokens of the answer.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub tokens: bool,
+    /// How urgently the answer should be generated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// Continue the last (assistant) message, instead of starting a new one.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub continue_final_message: bool,
+    /// Set to `false` when continuing the last message, so no new assistant turn is started.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub add_generation_prompt: Option<bool>,
 }
 
 impl<'a> ChatBody<'a> {
@@ -234,6 +938,7 @@ impl<'a> ChatBody<'a> {
                 Stopping {
                     maximum_tokens,
                     stop_sequences,
+                    include_stop_in_output: _,
                 },
             sampling:
                 ChatSampling {
@@ -243,13 +948,19 @@ impl<'a> ChatBody<'a> {
                     presence_penalty,
                 },
             logprobs,
+            guided_json,
+            documents,
+            extra: _,
+            tokens,
+            priority,
+            continue_final_message,
         } = task;
 
         Self {
             model,
             messages,
             max_tokens: *maximum_tokens,
-            stop: stop_sequences,
+            stop: Stop::new(stop_sequences),
             temperature: *temperature,
             top_p: *top_p,
             frequency_penalty: *frequency_penalty,
@@ -258,9 +969,49 @@ impl<'a> ChatBody<'a> {
             logprobs: logprobs.logprobs(),
             top_logprobs: logprobs.top_logprobs(),
             stream_options: None,
+            guided_json: guided_json.as_ref(),
+            documents,
+            tokens: *tokens,
+            priority: *priority,
+            continue_final_message: *continue_final_message,
+            add_generation_prompt: continue_final_message.then_some(false),
+        }
+    }
+
+    /// Body for the bare conversation, with every optional parameter left unset.
+    pub fn from_messages(model: &'a str, messages: &'a [Message<'a>]) -> Self {
+        Self {
+            model,
+            messages,
+            max_tokens: None,
+            stop: None,
+            temperature: None,
+            top_p: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            stream: false,
+            logprobs: false,
+            top_logprobs: None,
+            stream_options: None,
+            guided_json: None,
+            documents: &[],
+            tokens: false,
+            priority: None,
+            continue_final_message: false,
+            add_generation_prompt: None,
         }
     }
 
+    /// Use `defaults` for all sampling parameters not specified explicitly. `top_k` is ignored,
+    /// since it is not supported by the chat endpoint.
+    pub fn with_sampling_defaults(mut self, defaults: &Sampling) -> Self {
+        self.temperature = self.temperature.or(defaults.temperature);
+        self.top_p = self.top_p.or(defaults.top_p);
+        self.frequency_penalty = self.frequency_penalty.or(defaults.frequency_penalty);
+        self.presence_penalty = self.presence_penalty.or(defaults.presence_penalty);
+        self
+    }
+
     pub fn with_streaming(mut self) -> Self {
         self.stream = true;
         // Always set the `include_usage` to true, as currently we have not seen a
@@ -272,6 +1023,30 @@ impl<'a> ChatBody<'a> {
     }
 }
 
+impl TaskChat<'_> {
+    /// Shared by streamed and non-streamed requests, so both always send the same parameters and
+    /// only differ in the `stream` flag and options.
+    fn build_chat_request(
+        &self,
+        client: &reqwest::Client,
+        base: &str,
+        model: &str,
+        defaults: Option<&Sampling>,
+        stream: bool,
+    ) -> reqwest::RequestBuilder {
+        let mut body = ChatBody::new(model, self);
+        if let Some(defaults) = defaults {
+            body = body.with_sampling_defaults(defaults);
+        }
+        if stream {
+            body = body.with_streaming();
+        }
+        client
+            .post(format!("{base}/chat/completions"))
+            .json(&WithExtra::new(&body, &self.extra))
+    }
+}
+
 impl Task for TaskChat<'_> {
     type Output = ChatOutput;
 
@@ -283,22 +1058,90 @@ impl Task for TaskChat<'_> {
         base: &str,
         model: &str,
     ) -> reqwest::RequestBuilder {
-        let body = ChatBody::new(model, self);
-        client.post(format!("{base}/chat/completions")).json(&body)
+        self.build_chat_request(client, base, model, None, false)
     }
 
-    fn body_to_output(&self, mut response: Self::ResponseBody) -> Self::Output {
+    fn body_to_output(&self, response: Self::ResponseBody) -> Self::Output {
+        let mut output = response.into_output();
+        // A finish reason of `stop` does not tell whether a stop sequence has been generated.
+        let mut content = self
+            .stopping
+            .normalize_output(output.message.content.into_owned(), false);
+        if let (true, Some(prefill)) = (self.continue_final_message, self.messages.last()) {
+            content.insert_str(0, &prefill.content);
+        }
+        output.message.content = Cow::Owned(content);
+        output
+    }
+
+    fn maximum_tokens(&self) -> Option<u32> {
+        self.stopping.maximum_tokens
+    }
+}
+
+impl ChatResponse {
+    fn into_output(mut self) -> ChatOutput {
         let ResponseChoice {
             message,
             finish_reason,
             logprobs,
-        } = response.choices.pop().unwrap();
-        ChatOutput::new(
-            message,
-            finish_reason,
-            logprobs.unwrap_or_default().content,
-            response.usage,
-        )
+            citations,
+            tokens,
+            token_ids,
+        } = self.choices.pop().unwrap();
+        ChatOutput {
+            model: self.model,
+            citations,
+            tokens,
+            token_ids,
+            ..ChatOutput::new(
+                message,
+                finish_reason,
+                logprobs.unwrap_or_default().content,
+                self.usage,
+            )
+        }
+    }
+}
+
+/// A conversation without any further options. See [`crate::Client::chat_messages`].
+impl Task for &[Message<'_>] {
+    type Output = ChatOutput;
+
+    type ResponseBody = ChatResponse;
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        base: &str,
+        model: &str,
+    ) -> reqwest::RequestBuilder {
+        let body = ChatBody::from_messages(model, self);
+        client.post(format!("{base}/chat/completions")).json(&body)
+    }
+
+    fn body_to_output(&self, response: Self::ResponseBody) -> Self::Output {
+        response.into_output()
+    }
+}
+
+impl Task for WithSamplingDefaults<'_, &[Message<'_>]> {
+    type Output = ChatOutput;
+
+    type ResponseBody = ChatResponse;
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        base: &str,
+        model: &str,
+    ) -> reqwest::RequestBuilder {
+        let body = ChatBody::from_messages(model, self.task).with_sampling_defaults(self.defaults);
+        client.post(format!("{base}/chat/completions")).json(&body)
+    }
+
+    fn body_to_output(&self, response: Self::ResponseBody) -> Self::Output {
+        response.into_output()
     }
 }
 
@@ -310,6 +1153,10 @@ pub struct StreamMessage {
     /// The content of the current chat completion. Will be empty for the first chunk of every
     /// completion stream and non-empty for the remaining chunks.
     pub content: String,
+    /// Token usage of the stream so far, if the server reports it with every chunk. Not part of
+    /// the delta object on the wire, but of the chunk containing it.
+    #[serde(skip)]
+    pub usage: Option<Usage>,
 }
 
 /// One chunk of a chat completion stream.
@@ -325,6 +1172,10 @@ pub enum ChatChunk {
         /// The reason the model stopped generating tokens.
         #[serde(rename = "finish_reason")]
         reason: String,
+        /// Total token usage, if the server reports it in the same chunk as the finish reason. Not
+        /// part of the choice on the wire, but of the chunk containing it.
+        #[serde(skip)]
+        usage: Option<Usage>,
     },
 }
 
@@ -345,6 +1196,128 @@ pub enum StreamChatEvent {
     Usage(Usage),
 }
 
+impl StreamChatEvent {
+    /// The change to the message conveyed by this event. Usage reported by the event becomes
+    /// [`ChatDelta::usage`].
+    pub fn into_delta(self) -> ChatDelta {
+        match self {
+            StreamChatEvent::Chunk(ChatChunk::Delta { delta }) => ChatDelta {
+                role: delta.role.as_deref().and_then(Role::parse),
+                content: Some(delta.content).filter(|content| !content.is_empty()),
+                finish_reason: None,
+                usage: delta.usage,
+            },
+            StreamChatEvent::Chunk(ChatChunk::Finished { reason, usage }) => ChatDelta {
+                role: None,
+                content: None,
+                finish_reason: Some(FinishReason::from(reason.as_str())),
+                usage,
+            },
+            StreamChatEvent::Usage(usage) => ChatDelta {
+                role: None,
+                content: None,
+                finish_reason: None,
+                usage: Some(usage),
+            },
+        }
+    }
+}
+
+/// A single change to the message of a streamed chat answer. The first delta of a stream carries
+/// the role of the speaker, usually before any content arrives, so a user interface can label the
+/// answer right away. Subsequent deltas carry content, then the finish reason and finally the total
+/// token usage.
+/// Created from the events of [`crate::Client::stream_chat`] with
+/// [`StreamChatEvent::into_delta`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatDelta {
+    /// Role of the speaker. Only set for the first delta and only if it is a well known role.
+    pub role: Option<Role>,
+    /// Text appended to the message. `None` if this delta does not add any text.
+    pub content: Option<String>,
+    /// Why the model stopped generating. Only set for the last delta.
+    pub finish_reason: Option<FinishReason>,
+    /// Token usage of the stream so far. Every server reports the total usage once the answer is
+    /// complete, some also report the running usage with each chunk. `None` for chunks without
+    /// usage.
+    pub usage: Option<Usage>,
+}
+
+/// Assembles the events of a chat stream into a single [`ChatOutput`].
+pub(crate) struct ChatStreamCollector {
+    role: Option<String>,
+    content: String,
+    finish_reason: Option<String>,
+    usage: Option<Usage>,
+}
+
+impl ChatStreamCollector {
+    /// Collects the answer to `task`. If the task continues a prefill, the content starts with
+    /// it, like the content of a non-streamed answer.
+    pub fn new(task: &TaskChat<'_>) -> Self {
+        let content = match task.messages.last() {
+            Some(prefill) if task.continue_final_message => prefill.content.clone().into_owned(),
+            _ => String::new(),
+        };
+        Self {
+            role: None,
+            content,
+            finish_reason: None,
+            usage: None,
+        }
+    }
+
+    /// Accumulates the event. Returns the content appended to the message by this event, if any.
+    pub fn push(&mut self, event: StreamChatEvent) -> Option<&str> {
+        match event {
+            StreamChatEvent::Chunk(ChatChunk::Delta { delta }) => {
+                if let Some(role) = delta.role {
+                    self.role.get_or_insert(role);
+                }
+                // Running usage, superseded by the total usage at the end of the stream.
+                if let Some(usage) = delta.usage {
+                    self.usage = Some(usage);
+                }
+                let start = self.content.len();
+                self.content.push_str(&delta.content);
+                Some(&self.content[start..])
+            }
+            StreamChatEvent::Chunk(ChatChunk::Finished { reason, usage }) => {
+                self.finish_reason = Some(reason);
+                if let Some(usage) = usage {
+                    self.usage = Some(usage);
+                }
+                None
+            }
+            StreamChatEvent::Usage(usage) => {
+                self.usage = Some(usage);
+                None
+            }
+        }
+    }
+
+    /// The assembled output, once the stream has ended. Fails if the stream ended before the
+    /// model reported a finish reason and token usage.
+    pub fn finish(self) -> Result<ChatOutput, Error> {
+        let incomplete = |missing: &str| Error::InvalidStream {
+            deserialization_error: format!("Chat stream ended without {missing}"),
+        };
+        let finish_reason = self
+            .finish_reason
+            .ok_or_else(|| incomplete("a finish reason"))?;
+        let usage = self.usage.ok_or_else(|| incomplete("token usage"))?;
+        let role = self
+            .role
+            .unwrap_or_else(|| Role::Assistant.as_str().to_owned());
+        Ok(ChatOutput::new(
+            Message::new(role, self.content),
+            finish_reason,
+            Vec::new(),
+            usage,
+        ))
+    }
+}
+
 impl StreamTask for TaskChat<'_> {
     type Output = StreamChatEvent;
 
@@ -356,24 +1329,76 @@ impl StreamTask for TaskChat<'_> {
         base: &str,
         model: &str,
     ) -> reqwest::RequestBuilder {
-        let body = ChatBody::new(model, self).with_streaming();
-        client.post(format!("{base}/chat/completions")).json(&body)
+        self.build_chat_request(client, base, model, None, true)
     }
 
     fn body_to_output(mut response: Self::ResponseBody) -> Self::Output {
-        if let Some(usage) = response.usage {
-            StreamChatEvent::Usage(usage)
-        } else {
-            // We always expect there to be exactly one choice, as the `n` parameter is not
-            // supported by this crate.
-            StreamChatEvent::Chunk(
-                response
-                    .choices
-                    .pop()
-                    .expect("There must always be at least one choice"),
-            )
+        // We expect at most one choice, as the `n` parameter is not supported by this crate. Chunks
+        // without any choice only report the total usage.
+        match (response.choices.pop(), response.usage) {
+            (Some(ChatChunk::Delta { mut delta }), usage) => {
+                delta.usage = usage;
+                StreamChatEvent::Chunk(ChatChunk::Delta { delta })
+            }
+            (Some(ChatChunk::Finished { reason, .. }), usage) => {
+                StreamChatEvent::Chunk(ChatChunk::Finished { reason, usage })
+            }
+            (None, Some(usage)) => StreamChatEvent::Usage(usage),
+            (None, None) => panic!("There must always be at least one choice or usage"),
         }
     }
+
+    fn maximum_tokens(&self) -> Option<u32> {
+        self.stopping.maximum_tokens
+    }
+}
+
+impl Task for WithSamplingDefaults<'_, TaskChat<'_>> {
+    type Output = ChatOutput;
+
+    type ResponseBody = ChatResponse;
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        base: &str,
+        model: &str,
+    ) -> reqwest::RequestBuilder {
+        self.task
+            .build_chat_request(client, base, model, Some(self.defaults), false)
+    }
+
+    fn body_to_output(&self, response: Self::ResponseBody) -> Self::Output {
+        Task::body_to_output(self.task, response)
+    }
+
+    fn maximum_tokens(&self) -> Option<u32> {
+        self.task.stopping.maximum_tokens
+    }
+}
+
+impl StreamTask for WithSamplingDefaults<'_, TaskChat<'_>> {
+    type Output = StreamChatEvent;
+
+    type ResponseBody = StreamChatResponse;
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        base: &str,
+        model: &str,
+    ) -> reqwest::RequestBuilder {
+        self.task
+            .build_chat_request(client, base, model, Some(self.defaults), true)
+    }
+
+    fn body_to_output(response: Self::ResponseBody) -> Self::Output {
+        <TaskChat as StreamTask>::body_to_output(response)
+    }
+
+    fn maximum_tokens(&self) -> Option<u32> {
+        self.task.stopping.maximum_tokens
+    }
 }
 
 impl Logprobs {
@@ -393,3 +1418,298 @@ impl Logprobs {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn output_with_content(content: &str) -> ChatOutput {
+        ChatOutput::new(
+            Message::assistant(content.to_owned()),
+            "stop".to_owned(),
+            Vec::new(),
+            Usage {
+                prompt_tokens: 1,
+                completion_tokens: 1,
+            },
+        )
+    }
+
+    #[test]
+    fn default_chat_leaves_sampling_unset() {
+        let task = TaskChat {
+            messages: vec![Message::user("Hello")],
+            ..TaskChat::default()
+        };
+
+        assert_eq!(task.messages.len(), 1);
+        assert_eq!(task.sampling.temperature, None);
+        assert_eq!(task.sampling.top_p, None);
+        assert_eq!(task.stopping.maximum_tokens, None);
+    }
+
+    #[test]
+    fn chat_is_collected_from_messages() {
+        let history = vec![
+            Message::system("Be concise."),
+            Message::user("Hello"),
+            Message::assistant(""),
+        ];
+
+        let task: TaskChat = history
+            .into_iter()
+            .filter(|message| !message.content.is_empty())
+            .collect();
+
+        assert_eq!(
+            task,
+            TaskChat::with_messages(vec![Message::system("Be concise."), Message::user("Hello")])
+        );
+    }
+
+    #[test]
+    fn single_stop_sequence_is_sent_as_string() {
+        let task = TaskChat {
+            stopping: Stopping::from_stop_sequences(&["\n"]),
+            ..TaskChat::default()
+        };
+
+        let body = serde_json::to_value(ChatBody::new("model", &task)).unwrap();
+
+        assert_eq!(body["stop"], "\n");
+    }
+
+    #[test]
+    fn multiple_stop_sequences_are_sent_as_array() {
+        let task = TaskChat {
+            stopping: Stopping::from_stop_sequences(&["\n", "User:"]),
+            ..TaskChat::default()
+        };
+
+        let body = serde_json::to_value(ChatBody::new("model", &task)).unwrap();
+
+        assert_eq!(body["stop"], serde_json::json!(["\n", "User:"]));
+    }
+
+    #[test]
+    fn deterministic_chat_sends_zero_temperature() {
+        let task = TaskChat {
+            sampling: ChatSampling::BALANCED,
+            ..TaskChat::default()
+        }
+        .deterministic();
+
+        let body = serde_json::to_value(ChatBody::new("model", &task)).unwrap();
+
+        assert_eq!(body["temperature"], 0.0);
+        assert!(body.get("top_p").is_none());
+    }
+
+    #[test]
+    fn system_message_is_inserted_at_start() {
+        let task = TaskChat::with_message(Message::user("Hello")).with_system("Be concise.");
+
+        assert_eq!(
+            task.messages,
+            vec![Message::system("Be concise."), Message::user("Hello")]
+        );
+    }
+
+    #[test]
+    fn existing_system_message_is_replaced() {
+        let task = TaskChat::with_message(Message::user("Hello"))
+            .with_system("Be verbose.")
+            .with_system("Be concise.");
+
+        assert_eq!(task.messages[0], Message::system("Be concise."));
+        assert_eq!(task.messages.len(), 2);
+    }
+
+    #[test]
+    fn multiple_system_messages_are_reported() {
+        let task = TaskChat::with_messages(vec![
+            Message::system("Be concise."),
+            Message::user("Hello"),
+            Message::system("Answer in French."),
+        ]);
+
+        let warnings = task.validate().unwrap();
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].field, "messages");
+    }
+
+    #[test]
+    fn continuing_a_user_message_is_rejected() {
+        let task = TaskChat {
+            continue_final_message: true,
+            ..TaskChat::with_message(Message::user("Hello"))
+        };
+
+        let error = task.validate().unwrap_err();
+
+        assert!(
+            matches!(error, Error::InvalidParameter { field, .. } if field == "continue_final_message")
+        );
+    }
+
+    #[test]
+    fn sanitized_message_lacks_control_characters() {
+        let message = Message::user("Hello\u{0}\u{1b}[1m\tWorld\r\n\u{7f}");
+
+        let message = message.sanitized();
+
+        assert_eq!(message.content, "Hello[1m\tWorld\r\n");
+    }
+
+    #[test]
+    fn owned_messages_from_strings() {
+        let messages: Vec<OwnedMessage> = vec![
+            (Role::System, "Be concise.".to_owned()).into(),
+            "Hello".to_owned().into(),
+        ];
+
+        assert_eq!(
+            messages,
+            vec![Message::system("Be concise."), Message::user("Hello")]
+        );
+    }
+
+    #[test]
+    fn json_content_is_parsed() {
+        #[derive(Deserialize)]
+        struct Person {
+            name: String,
+        }
+        let output = output_with_content(r#"{"name": "Bob"}"#);
+
+        let person: Person = output.parse_json().unwrap();
+
+        assert_eq!(person.name, "Bob");
+    }
+
+    #[test]
+    fn invalid_json_content_is_reported_with_raw_content() {
+        let output = output_with_content("Sure, here is your JSON:");
+
+        let error = output.parse_json::<serde_json::Value>().unwrap_err();
+
+        assert!(
+            matches!(error, Error::ContentParse { content, .. } if content == "Sure, here is your JSON:")
+        );
+    }
+
+    #[test]
+    fn image_with_text_is_sent_as_content_parts() {
+        let message = Message::image_with_text(b"png", "image/png", "What is shown?");
+
+        let body = serde_json::to_value(&message).unwrap();
+
+        assert_eq!(
+            body,
+            json!({
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "What is shown?"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,cG5n"}}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn field_order_of_chat_body_is_stable() {
+        let task = TaskChat {
+            stopping: Stopping {
+                maximum_tokens: Some(64),
+                stop_sequences: &["\n"],
+                include_stop_in_output: false,
+            },
+            sampling: ChatSampling {
+                temperature: Some(0.5),
+                top_p: Some(0.9),
+                frequency_penalty: Some(0.1),
+                presence_penalty: Some(0.2),
+            },
+            logprobs: Logprobs::Top(2),
+            guided_json: Some(json!({"type": "string"})),
+            documents: vec![Document::new("Berlin is the capital.")],
+            tokens: true,
+            ..TaskChat::with_message(Message::user("Hello"))
+        };
+
+        let body = serde_json::to_string(&ChatBody::new("model", &task).with_streaming()).unwrap();
+
+        let expected = r#"{"model":"model","messages":[{"role":"user","content":"Hello"}],"max_tokens":64,"stop":"\n","temperature":0.5,"top_p":0.9,"frequency_penalty":0.1,"presence_penalty":0.2,"stream":true,"logprobs":true,"top_logprobs":2,"stream_options":{"include_usage":true},"guided_json":{"type":"string"},"documents":[{"text":"Berlin is the capital."}],"tokens":true}"#;
+        assert_eq!(body, expected);
+    }
+
+    #[test]
+    fn streamed_and_non_streamed_chat_send_same_parameters() {
+        let mut task = TaskChat {
+            stopping: Stopping {
+                maximum_tokens: Some(64),
+                stop_sequences: &["\n"],
+                include_stop_in_output: false,
+            },
+            sampling: ChatSampling {
+                temperature: Some(0.5),
+                top_p: Some(0.9),
+                frequency_penalty: Some(0.1),
+                presence_penalty: Some(0.2),
+            },
+            logprobs: Logprobs::Top(2),
+            ..TaskChat::with_message(Message::user("Hello"))
+        };
+        task.extra.insert("custom".to_owned(), json!(42));
+        let client = reqwest::Client::new();
+        let body_of = |request: reqwest::RequestBuilder| {
+            let request = request.build().unwrap();
+            let body = request.body().unwrap().as_bytes().unwrap();
+            serde_json::from_slice::<serde_json::Value>(body).unwrap()
+        };
+
+        let mut plain = body_of(Task::build_request(&task, &client, "http://api", "model"));
+        let mut streamed = body_of(StreamTask::build_request(
+            &task,
+            &client,
+            "http://api",
+            "model",
+        ));
+
+        assert_eq!(streamed["stream"], json!(true));
+        for body in [&mut plain, &mut streamed] {
+            let body = body.as_object_mut().unwrap();
+            body.remove("stream");
+            body.remove("stream_options");
+        }
+        assert_eq!(plain, streamed);
+        assert_eq!(plain["custom"], json!(42));
+    }
+
+    #[test]
+    fn chat_penalty_out_of_range_is_rejected() {
+        let valid = TaskChat {
+            sampling: ChatSampling {
+                frequency_penalty: Some(2.0),
+                ..ChatSampling::MOST_LIKELY
+            },
+            ..TaskChat::with_message(Message::user("Hello"))
+        };
+        let invalid = TaskChat {
+            sampling: ChatSampling {
+                frequency_penalty: Some(-2.5),
+                ..ChatSampling::MOST_LIKELY
+            },
+            ..valid.clone()
+        };
+
+        assert!(valid.validate().is_ok());
+        let error = invalid.validate().unwrap_err();
+        assert!(
+            matches!(error, Error::InvalidParameter { field, .. } if field == "frequency_penalty")
+        );
+    }
+}
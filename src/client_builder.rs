@@ -0,0 +1,363 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use reqwest::header::HeaderValue;
+
+#[cfg(feature = "cassette")]
+use crate::Cassette;
+use crate::{
+    http::{AuthHeader, HttpClient, Observer, DEFAULT_MAX_RESPONSE_BYTES, REDACTED},
+    CallMetrics, Client, ClientDialect, Error, RetryPolicy, Sampling,
+};
+
+/// Configures and creates a [`Client`]. Use this instead of [`Client::new`] if you want to change
+/// settings beyond host and API token.
+///
+/// ```no_run
+/// use aleph_alpha_client::{Client, Error};
+///
+/// fn client() -> Result<Client, Error> {
+///     Client::builder("https://inference-api.pharia.your-company.com")
+///         .with_api_token("my-token")
+///         .with_request_compression(1024)
+///         .build()
+/// }
+/// ```
+pub struct ClientBuilder {
+    host: String,
+    api_token: Option<String>,
+    request_compression: Option<usize>,
+    proxy: Option<ProxySettings>,
+    default_sampling: Sampling,
+    pool_max_idle_per_host: Option<usize>,
+    pool_idle_timeout: Option<Duration>,
+    http_version: HttpVersion,
+    retry_policy: RetryPolicy,
+    auth_header: Option<(String, Option<String>)>,
+    observer: Option<Observer>,
+    max_response_bytes: usize,
+    dialect: ClientDialect,
+    query: Vec<(String, String)>,
+    timeout_per_token: Option<Duration>,
+    #[cfg(feature = "cassette")]
+    cassette: Option<Cassette>,
+}
+
+/// HTTP version used to talk to the API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HttpVersion {
+    /// Negotiated with the server. HTTP/2 is only used for TLS connections, if the server offers it
+    /// via ALPN.
+    Negotiate,
+    Http1Only,
+    Http2PriorKnowledge,
+}
+
+/// Same default as `reqwest`.
+const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Proxy configured explicitly via [`ClientBuilder::with_proxy`].
+struct ProxySettings {
+    url: String,
+    basic_auth: Option<(String, String)>,
+    no_proxy: Option<String>,
+}
+
+impl fmt::Debug for ClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ClientBuilder");
+        debug
+            .field("host", &self.host)
+            .field("api_token", &self.api_token.as_ref().map(|_| REDACTED))
+            .field("request_compression", &self.request_compression)
+            .field("proxy", &self.proxy.as_ref().map(|proxy| &proxy.url))
+            .field("default_sampling", &self.default_sampling)
+            .field("pool_max_idle_per_host", &self.pool_max_idle_per_host)
+            .field("pool_idle_timeout", &self.pool_idle_timeout)
+            .field("http_version", &self.http_version)
+            .field("retry_policy", &self.retry_policy)
+            .field("auth_header", &self.auth_header)
+            .field("observer", &self.observer.as_ref().map(|_| "<observer>"))
+            .field("max_response_bytes", &self.max_response_bytes)
+            .field("dialect", &self.dialect)
+            .field("query", &self.query)
+            .field("timeout_per_token", &self.timeout_per_token);
+        #[cfg(feature = "cassette")]
+        debug.field("cassette", &self.cassette);
+        debug.finish()
+    }
+}
+
+impl ClientBuilder {
+    /// In production you typically would want set this to
+    /// <https://inference-api.pharia.your-company.com>. Yet you may want to use a different
+    /// instance for testing.
+    pub fn new(host: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            api_token: None,
+            request_compression: None,
+            proxy: None,
+            default_sampling: Sampling::MOST_LIKELY,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout: Some(DEFAULT_POOL_IDLE_TIMEOUT),
+            http_version: HttpVersion::Negotiate,
+            retry_policy: RetryPolicy::NONE,
+            auth_header: None,
+            observer: None,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            dialect: ClientDialect::AlephAlpha,
+            query: Vec::new(),
+            timeout_per_token: None,
+            #[cfg(feature = "cassette")]
+            cassette: None,
+        }
+    }
+
+    /// Default API token used to authenticate all requests. If not set, a token must be specified
+    /// for each request using [`crate::How::api_token`]. A token which can not be sent in a header,
+    /// e.g. because of a trailing newline, causes [`Self::build`] to fail.
+    pub fn with_api_token(mut self, api_token: impl Into<String>) -> Self {
+        self.api_token = Some(api_token.into());
+        self
+    }
+
+    /// Send the API token in the header `name`, instead of `Authorization`. The token is preceded
+    /// by `scheme` and a space, if specified. By default the token is sent as
+    /// `Authorization: Bearer <token>`. E.g. for a gateway expecting `Api-Key: <token>` use
+    /// `with_auth_header("Api-Key", None)`. An invalid header name or a scheme which can not be part
+    /// of a header value causes [`Self::build`] to fail.
+    pub fn with_auth_header(mut self, name: impl Into<String>, scheme: Option<&str>) -> Self {
+        self.auth_header = Some((name.into(), scheme.map(str::to_owned)));
+        self
+    }
+
+    /// Compress request bodies with gzip and send them with a `Content-Encoding: gzip` header.
+    /// Only bodies with at least `min_body_size` bytes are compressed, since for tiny bodies the
+    /// overhead of compression outweighs the savings. A value around `1024` is a sensible choice.
+    ///
+    /// Disabled by default. Useful for large multimodal or document prompts and batch embeddings.
+    pub fn with_request_compression(mut self, min_body_size: usize) -> Self {
+        self.request_compression = Some(min_body_size);
+        self
+    }
+
+    /// Route all requests through the proxy at `url`, e.g. `http://proxy.your-company.com:3128`.
+    ///
+    /// If no proxy is set explicitly, the client honours the `HTTPS_PROXY`, `HTTP_PROXY`,
+    /// `ALL_PROXY` and `NO_PROXY` environment variables. Setting a proxy here replaces these.
+    pub fn with_proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(ProxySettings {
+            url: url.into(),
+            basic_auth: None,
+            no_proxy: None,
+        });
+        self
+    }
+
+    /// Authenticate against the proxy set with [`Self::with_proxy`] using basic auth. Has no
+    /// effect if no proxy has been set.
+    pub fn with_proxy_basic_auth(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        if let Some(proxy) = &mut self.proxy {
+            proxy.basic_auth = Some((username.into(), password.into()));
+        }
+        self
+    }
+
+    /// Comma separated list of hosts which are reached directly rather than through the proxy set
+    /// with [`Self::with_proxy`], e.g. `localhost,.internal.your-company.com`. Same format as the
+    /// `NO_PROXY` environment variable. Has no effect if no proxy has been set.
+    pub fn with_no_proxy(mut self, hosts: impl Into<String>) -> Self {
+        if let Some(proxy) = &mut self.proxy {
+            proxy.no_proxy = Some(hosts.into());
+        }
+        self
+    }
+
+    /// Sampling parameters used for every completion and chat, which does not specify them itself.
+    /// Parameters set on the task always take precedence. `top_k` is only applied to completions,
+    /// since chat does not support it.
+    pub fn with_default_sampling(mut self, sampling: Sampling) -> Self {
+        self.default_sampling = sampling;
+        self
+    }
+
+    /// Maximum number of idle connections kept open per host for reuse. By default there is no
+    /// limit. Lower it to save resources, if you only send few requests.
+    pub fn with_pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max_idle);
+        self
+    }
+
+    /// How long an idle connection is kept open for reuse. `None` keeps idle connections open
+    /// forever. Defaults to 90 seconds. If you send thousands of requests per second, increasing
+    /// this avoids the cost of establishing new connections after short pauses.
+    pub fn with_pool_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.pool_idle_timeout = timeout;
+        self
+    }
+
+    /// Only speak HTTP/1.1, even if the server offers HTTP/2. Useful for gateways which stall
+    /// under HTTP/2 multiplexing. By default the HTTP version is negotiated with the server.
+    /// Overrides [`Self::with_http2_prior_knowledge`].
+    pub fn with_http1_only(mut self, enabled: bool) -> Self {
+        self.set_http_version(enabled, HttpVersion::Http1Only);
+        self
+    }
+
+    /// Speak HTTP/2 right away, without negotiating it first. Only use this if you know the
+    /// server supports HTTP/2, e.g. for a gateway reached via plain HTTP. Overrides
+    /// [`Self::with_http1_only`].
+    pub fn with_http2_prior_knowledge(mut self, enabled: bool) -> Self {
+        self.set_http_version(enabled, HttpVersion::Http2PriorKnowledge);
+        self
+    }
+
+    /// Retry requests which failed due to transient conditions, like an overloaded model. By
+    /// default requests are not retried. See [`RetryPolicy`].
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Abort reading a response body once it exceeds `max_response_bytes` and fail with
+    /// [`Error::ResponseTooLarge`]. Protects your service against running out of memory, if the
+    /// server or a gateway in between misbehaves. For streams the limit applies to the sum of all
+    /// events. Defaults to 128 MiB.
+    pub fn with_max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = max_response_bytes;
+        self
+    }
+
+    /// Choose the flavour of API the client talks to. Defaults to [`ClientDialect::AlephAlpha`].
+    /// Use [`ClientDialect::OpenAiCompatible`] for models served behind an OpenAI compatible
+    /// gateway.
+    pub fn with_dialect(mut self, dialect: ClientDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Append the query parameter `name=value` to every request, e.g. `api-version=2024-01` for
+    /// gateways which require it. Can be called multiple times to add several parameters. By
+    /// default no parameters besides the ones of the API are sent.
+    pub fn with_query_parameter(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.query.push((name.into(), value.into()));
+        self
+    }
+
+    /// Scale the timeout of each request with the number of tokens it may generate, so long
+    /// generations are not killed by a timeout which is tight enough for short ones. The effective
+    /// timeout is
+    ///
+    /// `client_timeout + per_token * maximum_tokens`
+    ///
+    /// with [`crate::How::client_timeout`] as base and `maximum_tokens` taken from the stopping
+    /// criteria of the completion or chat task. Requests without `maximum_tokens`, e.g.
+    /// embeddings, use `client_timeout` alone. By default `per_token` is `None` and all requests
+    /// use `client_timeout` regardless of their length. Consider lowering `client_timeout` from
+    /// its default of 305 seconds to a base like 10 seconds, once you set this.
+    pub fn with_timeout_per_token(mut self, per_token: Option<Duration>) -> Self {
+        self.timeout_per_token = per_token;
+        self
+    }
+
+    /// Record all requests and their responses to the cassette, or answer them from it without
+    /// sending them, depending on how the [`Cassette`] has been created. Intended for tests.
+    /// Retries and the translation of error responses behave the same as without a cassette.
+    #[cfg(feature = "cassette")]
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Invoke `observer` after every successful call with its latency and the sizes of request
+    /// and response body, e.g. to monitor bandwidth costs of large multimodal prompts. For streams
+    /// it is invoked once the stream has ended, and not at all if it is dropped early. Keep the
+    /// observer cheap, since it is executed on the task awaiting the response.
+    pub fn with_observer(
+        mut self,
+        observer: impl Fn(&CallMetrics) + Send + Sync + 'static,
+    ) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    fn set_http_version(&mut self, enabled: bool, version: HttpVersion) {
+        if enabled {
+            self.http_version = version;
+        } else if self.http_version == version {
+            self.http_version = HttpVersion::Negotiate;
+        }
+    }
+
+    /// Create the client with the specified settings.
+    pub fn build(self) -> Result<Client, Error> {
+        let auth_header = match self.auth_header {
+            Some((name, scheme)) => {
+                // Checked on its own, so an invalid scheme is not reported as an invalid token.
+                if let Some(scheme) = &scheme {
+                    HeaderValue::from_str(&format!("{scheme} x")).map_err(|_| {
+                        Error::InvalidParameter {
+                            field: "auth_header".to_owned(),
+                            message: format!("'{scheme}' is not a valid authentication scheme"),
+                        }
+                    })?;
+                }
+                AuthHeader {
+                    name: name.parse().map_err(|_| Error::InvalidParameter {
+                        field: "auth_header".to_owned(),
+                        message: format!("'{name}' is not a valid header name"),
+                    })?,
+                    scheme,
+                }
+            }
+            None => AuthHeader::default(),
+        };
+        // Tokens passed per request via `How::api_token` are checked once they are sent.
+        if let Some(api_token) = &self.api_token {
+            auth_header.value(api_token)?;
+        }
+        let mut http = reqwest::ClientBuilder::new();
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            http = http.pool_max_idle_per_host(max_idle);
+        }
+        http = http.pool_idle_timeout(self.pool_idle_timeout);
+        http = match self.http_version {
+            HttpVersion::Negotiate => http,
+            HttpVersion::Http1Only => http.http1_only(),
+            HttpVersion::Http2PriorKnowledge => http.http2_prior_knowledge(),
+        };
+        if let Some(settings) = self.proxy {
+            let mut proxy = reqwest::Proxy::all(settings.url)?;
+            if let Some((username, password)) = &settings.basic_auth {
+                proxy = proxy.basic_auth(username, password);
+            }
+            if let Some(no_proxy) = &settings.no_proxy {
+                proxy = proxy.no_proxy(reqwest::NoProxy::from_string(no_proxy));
+            }
+            http = http.proxy(proxy);
+        }
+        let http_client = HttpClient::with_reqwest_client(self.host, http.build()?, self.api_token)
+            .with_request_compression(self.request_compression)
+            .with_retry_policy(self.retry_policy)
+            .with_auth_header(auth_header)
+            .with_observer(self.observer)
+            .with_max_response_bytes(self.max_response_bytes)
+            .with_dialect(self.dialect)
+            .with_query(self.query)
+            .with_timeout_per_token(self.timeout_per_token);
+        #[cfg(feature = "cassette")]
+        let http_client = http_client.with_cassette(self.cassette);
+        Ok(Client {
+            http_client,
+            default_sampling: self.default_sampling,
+        })
+    }
+}
@@ -1,10 +1,14 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Range};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{http::Task, Distribution, Logprob, Logprobs, Prompt, StreamTask, Usage};
+use crate::{
+    extra::WithExtra, http::Task, validation::check_penalty, Distribution, Error, Logprob,
+    Logprobs, Priority, Prompt, StreamTask, Usage, ValidationWarning,
+};
 
 /// Completes a prompt. E.g. continues a text.
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskCompletion<'a> {
     /// The prompt (usually text) to be completed. Unconditional completion can be started with an
     /// empty string. The prompt may contain a zero shot or few shot task.
@@ -18,6 +22,51 @@ pub struct TaskCompletion<'a> {
     /// Wether you are interessted in the probabilities of the sampled tokens, or most likely
     /// tokens.
     pub logprobs: Logprobs,
+    /// Some inference optimizations may introduce nondeterminism into the completion. Set this to
+    /// `Some(true)` to disable them, e.g. for reproducible evaluations in combination with
+    /// [`Sampling::MOST_LIKELY`]. `None` leaves the decision to the API, which currently keeps
+    /// optimizations enabled.
+    pub disable_optimizations: Option<bool>,
+    /// JSON schema the output of the model is forced to conform to, using constrained decoding on
+    /// the server side. This is stronger than asking the model for JSON in the prompt. Send under
+    /// the `guided_json` parameter, which is supported by models served by vLLM based workers.
+    /// Other deployments may ignore or reject it.
+    pub guided_json: Option<serde_json::Value>,
+    /// Return the individual tokens the completion consists of in
+    /// [`CompletionOutput::completion_tokens`].
+    pub tokens: bool,
+    /// Additional parameters merged into the request body, e.g. toggles specific to your
+    /// deployment which this crate does not know about. Parameters set via the typed fields take
+    /// precedence, i.e. an entry clashing with a parameter the typed fields already send is
+    /// ignored. See [`Self::with_extra`].
+    pub extra: serde_json::Map<String, serde_json::Value>,
+    /// How urgently the completion should be processed. Only supported by some deployments, see
+    /// [`Priority`]. `None` omits the parameter.
+    pub priority: Option<Priority>,
+}
+
+/// Number of tokens a [`TaskCompletion`] created with [`Default::default`] generates at most.
+pub const DEFAULT_MAXIMUM_TOKENS: u32 = 256;
+
+/// An empty prompt, which is completed with the most likely tokens. Generation is capped at
+/// [`DEFAULT_MAXIMUM_TOKENS`] to avoid surprisingly long (and expensive) completions. Set
+/// `stopping.maximum_tokens` to `None` explicitly, if you want the model to generate until it
+/// reaches a stop sequence or its context limit.
+impl Default for TaskCompletion<'_> {
+    fn default() -> Self {
+        TaskCompletion {
+            prompt: Prompt::from_text(""),
+            stopping: Stopping::from_maximum_tokens(DEFAULT_MAXIMUM_TOKENS),
+            sampling: Sampling::MOST_LIKELY,
+            special_tokens: false,
+            logprobs: Logprobs::No,
+            disable_optimizations: None,
+            guided_json: None,
+            tokens: false,
+            extra: serde_json::Map::new(),
+            priority: None,
+        }
+    }
 }
 
 impl<'a> TaskCompletion<'a> {
@@ -29,6 +78,11 @@ impl<'a> TaskCompletion<'a> {
             sampling: Sampling::MOST_LIKELY,
             special_tokens: false,
             logprobs: Logprobs::No,
+            disable_optimizations: None,
+            guided_json: None,
+            tokens: false,
+            extra: serde_json::Map::new(),
+            priority: None,
         }
     }
 
@@ -52,13 +106,86 @@ impl<'a> TaskCompletion<'a> {
         self.logprobs = logprobs;
         self
     }
+
+    /// Return the individual tokens of the completion. See [`Self::tokens`].
+    pub fn with_tokens(mut self) -> Self {
+        self.tokens = true;
+        self
+    }
+
+    /// Disable inference optimizations which may introduce nondeterminism.
+    pub fn with_optimizations_disabled(mut self) -> Self {
+        self.disable_optimizations = Some(true);
+        self
+    }
+
+    /// Force the completion to conform to the given JSON schema. See [`Self::guided_json`].
+    pub fn with_json_schema(mut self, schema: serde_json::Value) -> Self {
+        self.guided_json = Some(schema);
+        self
+    }
+
+    /// Send an additional parameter along with the typed ones. See [`Self::extra`].
+    pub fn with_extra(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.extra.insert(key.into(), value);
+        self
+    }
+
+    /// Set how urgently the completion should be processed. See [`Self::priority`].
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Configure the task for reproducible results, e.g. for evaluations. Sets the temperature to
+    /// 0, disables `top_k` and `top_p` and disables optimizations. Penalties are kept. Any of these
+    /// can be changed again afterwards. `top_k` and `top_p` are set to `0` rather than `None`, so
+    /// the default sampling of the client does not fill them in again.
+    ///
+    /// This is the correct configuration on the client side, yet the server does not guarantee
+    /// identical completions for identical requests, e.g. due to batching on the GPU. A seed is not
+    /// sent, since the API does not accept one.
+    pub fn deterministic(mut self) -> Self {
+        self.sampling.temperature = Some(0.0);
+        self.sampling.top_k = Some(0);
+        self.sampling.top_p = Some(0.0);
+        self.disable_optimizations = Some(true);
+        self
+    }
+
+    /// Check the task for mistakes before sending it. Parameters the API would reject are reported
+    /// as [`Error::InvalidParameter`]. Combinations which are accepted, but likely not what you
+    /// intended, are returned as warnings. This is optional and not invoked by the client when
+    /// executing the task.
+    ///
+    /// Relations between parameters this crate does not expose are not checked. E.g. the API
+    /// requires `best_of >= n`, but a task always requests exactly one completion and does not
+    /// support `best_of`.
+    ///
+    /// Penalties must be within `[-2, 2]`. If your deployment accepts a wider range, simply do not
+    /// call this method.
+    pub fn validate(&self) -> Result<Vec<ValidationWarning>, Error> {
+        check_penalty("frequency_penalty", self.sampling.frequency_penalty)?;
+        check_penalty("presence_penalty", self.sampling.presence_penalty)?;
+        let mut warnings = Vec::new();
+        if self.stopping.maximum_tokens == Some(0) && matches!(self.logprobs, Logprobs::No) {
+            warnings.push(ValidationWarning::new(
+                "maximum_tokens",
+                "is 0 and no logprobs are requested, so the completion will be empty",
+            ));
+        }
+        Ok(warnings)
+    }
 }
 
 /// Sampling controls how the tokens ("words") are selected for the completion.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Sampling {
     /// A temperature encourages the model to produce less probable outputs ("be more creative").
-    /// Values are expected to be between 0 and 1. Try high values for a more random ("creative")
-    /// response.
+    /// The logits are divided by the temperature before sampling, so `1` leaves the distribution
+    /// untouched and values close to `0` approach always choosing the most likely token. Values
+    /// between 0 and 2 are accepted. Try high values for a more random ("creative") response.
+    /// Values the server does not accept are reported as [`crate::Error::InvalidParameter`].
     pub temperature: Option<f64>,
     /// Introduces random sampling for generated tokens by randomly selecting the next token from
     /// the k most likely options. A value larger than 1 encourages the model to be more creative.
@@ -66,7 +193,12 @@ pub struct Sampling {
     pub top_k: Option<u32>,
     /// Introduces random sampling for generated tokens by randomly selecting the next token from
     /// the smallest possible set of tokens whose cumulative probability exceeds the probability
-    /// top_p. Set to 0 to get the same behaviour as `None`.
+    /// top_p. Set to 0 to get the same behaviour as `None`, i.e. the parameter is omitted from the
+    /// request. This is the same for completions and chats (see [`crate::ChatSampling::top_p`]).
+    ///
+    /// `temperature`, `top_k` and `top_p` can be combined. The logits are divided by the
+    /// temperature first, then only the `top_k` most likely tokens are kept and of those the
+    /// smallest set exceeding the cumulative probability `top_p`.
     pub top_p: Option<f64>,
     /// When specified, this number will decrease (or increase) the likelihood of repeating tokens
     /// that were mentioned prior in the completion. The penalty is cumulative. The more a token
@@ -96,6 +228,35 @@ impl Sampling {
         frequency_penalty: None,
         presence_penalty: None,
     };
+
+    /// Favours variety over precision. A good starting point for brainstorming or creative
+    /// writing.
+    pub const CREATIVE: Self = Sampling {
+        temperature: Some(0.8),
+        top_k: None,
+        top_p: None,
+        frequency_penalty: None,
+        presence_penalty: None,
+    };
+
+    /// Some variety, while cutting off unlikely tokens. A good starting point for conversations.
+    pub const BALANCED: Self = Sampling {
+        temperature: Some(0.5),
+        top_k: None,
+        top_p: Some(0.9),
+        frequency_penalty: None,
+        presence_penalty: None,
+    };
+
+    /// Sticks closely to the most likely tokens, with a little randomness. A good starting point
+    /// for question answering or extraction.
+    pub const PRECISE: Self = Sampling {
+        temperature: Some(0.2),
+        top_k: None,
+        top_p: None,
+        frequency_penalty: None,
+        presence_penalty: None,
+    };
 }
 
 impl Default for Sampling {
@@ -104,13 +265,30 @@ impl Default for Sampling {
     }
 }
 
+/// `true` if a sampling parameter like `top_k` or `top_p` is disabled. `0` disables these parameters
+/// just like `None`, so both are omitted from the request. This way completions and chats treat
+/// them alike, even though OpenAI compatible chat routes would reject a `top_p` of `0`.
+pub(crate) fn is_none_or_zero<T: Default + PartialEq>(value: &Option<T>) -> bool {
+    value.as_ref().is_none_or(|value| *value == T::default())
+}
+
+/// Executes a task with the default sampling configured for the [`crate::Client`]. Any sampling
+/// parameter left `None` by the task, is taken from the defaults.
+pub(crate) struct WithSamplingDefaults<'a, T> {
+    pub task: &'a T,
+    pub defaults: &'a Sampling,
+}
+
 /// Controls the conditions under which the language models stops generating text.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Stopping<'a> {
     /// The maximum number of tokens to be generated. Completion will terminate after the maximum
     /// number of tokens is reached. Increase this value to allow for longer outputs. A text is split
     /// into tokens. Usually there are more tokens than words. The total number of tokens of prompt
     /// and maximum_tokens depends on the model.
     /// If maximum tokens is set to None, no outside limit is opposed on the number of maximum tokens.
+    /// A value of `0` generates nothing, which is only useful if you are interested in the
+    /// logprobs of the prompt, e.g. for scoring it.
     /// The model will generate tokens until it generates one of the specified stop_sequences or it
     /// reaches its technical limit, which usually is its context window.
     pub maximum_tokens: Option<u32>,
@@ -120,7 +298,26 @@ pub struct Stopping<'a> {
     /// answer, the model will be likely to generate "Question: ". "Question: " may therefore be used
     /// as stop sequence in order not to have the model generate more questions but rather restrict
     /// text generation to the answers.
+    ///
+    /// The stop sequence which ended the generation is not part of the returned completion,
+    /// unless [`Self::include_stop_in_output`] is set.
+    ///
+    /// In chat requests a single stop sequence is sent as a bare string rather than an array of
+    /// one, which is the form understood by all OpenAI compatible deployments.
     pub stop_sequences: &'a [&'a str],
+    /// Whether the stop sequence which ended the generation is part of the returned completion or
+    /// chat answer. Deployments differ in whether they strip it, so the text is normalized on the
+    /// client side:
+    ///
+    /// * `false` (default): A trailing stop sequence is removed.
+    /// * `true`: A trailing stop sequence is kept. If the deployment stripped it, it is appended
+    ///   again, as long as it is known which one ended the generation. This is the case for
+    ///   completions reporting `stop_sequence_reached` as finish reason, with a single stop
+    ///   sequence. Chat responses only report `stop`, which is also used for a natural end of the
+    ///   answer, so a stripped stop sequence can not be restored for them.
+    ///
+    /// Streamed outputs are passed on unchanged.
+    pub include_stop_in_output: bool,
 }
 
 impl<'a> Stopping<'a> {
@@ -128,21 +325,44 @@ impl<'a> Stopping<'a> {
     pub const NO_TOKEN_LIMIT: Self = Stopping {
         maximum_tokens: None,
         stop_sequences: &[],
+        include_stop_in_output: false,
     };
 
     /// Stop once the model has reached maximum_tokens.
     pub fn from_maximum_tokens(maximum_tokens: u32) -> Self {
         Self {
             maximum_tokens: Some(maximum_tokens),
-            stop_sequences: &[],
+            ..Self::NO_TOKEN_LIMIT
         }
     }
 
     pub fn from_stop_sequences(stop_sequences: &'a [&'a str]) -> Self {
         Self {
-            maximum_tokens: None,
             stop_sequences,
+            ..Self::NO_TOKEN_LIMIT
+        }
+    }
+
+    /// Keep the stop sequence which ended the generation in the output. See
+    /// [`Self::include_stop_in_output`].
+    pub fn with_stop_in_output(mut self) -> Self {
+        self.include_stop_in_output = true;
+        self
+    }
+
+    /// Applies [`Self::include_stop_in_output`] to the text generated by the model. `stopped` is
+    /// `true` if the finish reason states unambiguously that a stop sequence ended the generation.
+    pub(crate) fn normalize_output(&self, mut text: String, stopped: bool) -> String {
+        let trailing = self
+            .stop_sequences
+            .iter()
+            .find(|stop| !stop.is_empty() && text.ends_with(**stop));
+        match (trailing, self.include_stop_in_output, self.stop_sequences) {
+            (Some(stop), false, _) => text.truncate(text.len() - stop.len()),
+            (None, true, [stop]) if stopped => text.push_str(stop),
+            _ => (),
         }
+        text
     }
 }
 
@@ -153,6 +373,10 @@ impl Default for Stopping<'_> {
 }
 
 /// Body send to the Aleph Alpha API on the POST `/completion` Route
+///
+/// Fields are serialized in the order they are declared, so the body of a given task is always the
+/// same string. Do not reorder them and append new fields at the end. Bodies of tasks with
+/// [`TaskCompletion::extra`] parameters are the exception, their keys are in alphabetical order.
 #[derive(Serialize, Debug)]
 struct BodyCompletion<'a> {
     /// Name of the model tasked with completing the prompt. E.g. `luminous-base"`.
@@ -172,9 +396,9 @@ struct BodyCompletion<'a> {
     pub stop_sequences: &'a [&'a str],
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "is_none_or_zero")]
     pub top_k: Option<u32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "is_none_or_zero")]
     pub top_p: Option<f64>,
     /// If true, the response will be streamed.
     #[serde(skip_serializing_if = "std::ops::Not::not")]
@@ -194,6 +418,15 @@ struct BodyCompletion<'a> {
     pub log_probs: Option<u8>,
     #[serde(skip_serializing_if = "std::ops::Not::not")]
     pub tokens: bool,
+    /// Disables inference optimizations, which may introduce nondeterminism.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable_optimizations: Option<bool>,
+    /// JSON schema the completion is forced to conform to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guided_json: Option<&'a serde_json::Value>,
+    /// How urgently the completion should be processed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
 }
 
 impl<'a> BodyCompletion<'a> {
@@ -204,6 +437,11 @@ impl<'a> BodyCompletion<'a> {
             sampling,
             special_tokens,
             logprobs,
+            disable_optimizations,
+            guided_json,
+            tokens,
+            extra: _,
+            priority,
         } = task;
         Self {
             model,
@@ -218,21 +456,37 @@ impl<'a> BodyCompletion<'a> {
             frequency_penalty: sampling.frequency_penalty,
             presence_penalty: sampling.presence_penalty,
             log_probs: logprobs.to_logprobs_num(),
-            tokens: logprobs.to_tokens(),
+            tokens: *tokens || logprobs.to_tokens(),
+            disable_optimizations: *disable_optimizations,
+            guided_json: guided_json.as_ref(),
+            priority: *priority,
         }
     }
     pub fn with_streaming(mut self) -> Self {
         self.stream = true;
         self
     }
+
+    /// Use `defaults` for all sampling parameters not specified explicitly.
+    pub fn with_sampling_defaults(mut self, defaults: &Sampling) -> Self {
+        self.temperature = self.temperature.or(defaults.temperature);
+        self.top_k = self.top_k.or(defaults.top_k);
+        self.top_p = self.top_p.or(defaults.top_p);
+        self.frequency_penalty = self.frequency_penalty.or(defaults.frequency_penalty);
+        self.presence_penalty = self.presence_penalty.or(defaults.presence_penalty);
+        self
+    }
 }
 
 #[derive(Deserialize, Debug, PartialEq)]
 pub struct ResponseCompletion {
     model_version: String,
     completions: Vec<DeserializedCompletion>,
-    num_tokens_prompt_total: u32,
-    num_tokens_generated: u32,
+    #[serde(default)]
+    num_tokens_prompt_total: Option<u32>,
+    #[serde(default)]
+    num_tokens_generated: Option<u32>,
+    optimized_prompt: Option<Prompt<'static>>,
 }
 
 #[derive(Deserialize, Debug, PartialEq)]
@@ -246,13 +500,125 @@ struct DeserializedCompletion {
     completion_tokens: Vec<String>,
 }
 
-/// Completion and metainformation returned by a completion task
+/// Order in which [`crate::Client::complete_stream`] yields the outputs of concurrently executed
+/// tasks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CompletionOrder {
+    /// In the order the tasks have been submitted, so outputs can be zipped with their inputs. A
+    /// slow completion holds back the ones after it, even if they have already finished.
+    #[default]
+    AsSubmitted,
+    /// As soon as each completion finishes. Maximizes throughput, but you need to keep track of
+    /// which output belongs to which task yourself.
+    AsCompleted,
+}
+
+/// Completion and metainformation returned by a completion task. Exposes all fields of the
+/// response. Use [`crate::Client::complete_text`] if you are only interested in the text.
 #[derive(Deserialize, Debug, PartialEq)]
 pub struct CompletionOutput {
     pub completion: String,
     pub finish_reason: String,
+    /// Name and version of the model which generated the completion, as reported by the API.
+    /// Useful to tell apart outputs of different versions of a model in logs or evaluations.
+    #[serde(default)]
+    pub model_version: String,
+    /// Empty unless requested via [`TaskCompletion::logprobs`].
     pub logprobs: Vec<Distribution>,
+    /// Zero for counts missing in the response. See [`Self::num_tokens_prompt_total`] and
+    /// [`Self::num_tokens_generated`] to tell them apart.
     pub usage: Usage,
+    /// Number of tokens in the prompt, exactly as counted by the server. Unlike tokenizing the
+    /// prompt yourself, this takes e.g. optimizations of the prompt into account. `None` if the
+    /// server did not report it.
+    #[serde(default)]
+    pub num_tokens_prompt_total: Option<u32>,
+    /// Number of tokens the model generated, as counted by the server. `None` if the server did
+    /// not report it.
+    #[serde(default)]
+    pub num_tokens_generated: Option<u32>,
+    /// The prompt as it has actually been processed by the model, if the server applied
+    /// optimizations to it. E.g. whitespace at the end of the prompt may have been removed.
+    /// `None` if the prompt has been left unchanged or the server did not report it. Use
+    /// [`TaskCompletion::with_optimizations_disabled`] to prevent the rewrite.
+    pub optimized_prompt: Option<Prompt<'static>>,
+    /// The tokens the completion consists of, in order, if requested via
+    /// [`TaskCompletion::tokens`]. Together with their log probabilities, if requested via
+    /// [`TaskCompletion::logprobs`], and their position in the raw completion.
+    pub completion_tokens: Option<Vec<CompletionToken>>,
+}
+
+/// A token of a completion, see [`CompletionOutput::completion_tokens`].
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct CompletionToken {
+    /// The token as reported by the API.
+    pub token: String,
+    /// Log probability of the model sampling this token. `None` unless requested via
+    /// [`TaskCompletion::logprobs`].
+    pub logprob: Option<f64>,
+    /// Bytes of the raw completion this token spans, e.g. for highlighting. Concatenating the
+    /// tokens yields the raw completion. Note that the completion may differ from the raw
+    /// completion, unless special tokens are requested or optimizations are disabled.
+    pub byte_range: Range<usize>,
+}
+
+/// Pairs each token with its log probability and its position in the raw completion.
+fn completion_tokens_with_ranges(
+    completion_tokens: &[String],
+    log_probs: &[HashMap<String, f64>],
+) -> Vec<CompletionToken> {
+    let mut start = 0;
+    completion_tokens
+        .iter()
+        .enumerate()
+        .map(|(index, token)| {
+            let byte_range = start..start + token.len();
+            start = byte_range.end;
+            CompletionToken {
+                token: token.clone(),
+                logprob: log_probs.get(index).and_then(|map| map.get(token)).copied(),
+                byte_range,
+            }
+        })
+        .collect()
+}
+
+impl CompletionOutput {
+    /// Number of tokens the model generated. Prefers the count reported by the server in
+    /// [`Self::num_tokens_generated`]. If the server did not report it, falls back to counting
+    /// [`Self::completion_tokens`], in case they have been requested. `None` if neither is
+    /// available.
+    pub fn generated_tokens(&self) -> Option<u32> {
+        self.num_tokens_generated.or_else(|| {
+            self.completion_tokens
+                .as_ref()
+                .map(|tokens| tokens.len() as u32)
+        })
+    }
+}
+
+impl TaskCompletion<'_> {
+    /// Shared by streamed and non-streamed requests, so both always send the same parameters and
+    /// only differ in the `stream` flag.
+    fn build_completion_request(
+        &self,
+        client: &reqwest::Client,
+        base: &str,
+        model: &str,
+        defaults: Option<&Sampling>,
+        stream: bool,
+    ) -> reqwest::RequestBuilder {
+        let mut body = BodyCompletion::new(model, self);
+        if let Some(defaults) = defaults {
+            body = body.with_sampling_defaults(defaults);
+        }
+        if stream {
+            body = body.with_streaming();
+        }
+        client
+            .post(format!("{base}/complete"))
+            .json(&WithExtra::new(&body, &self.extra))
+    }
 }
 
 impl Task for TaskCompletion<'_> {
@@ -266,8 +632,7 @@ impl Task for TaskCompletion<'_> {
         base: &str,
         model: &str,
     ) -> reqwest::RequestBuilder {
-        let body = BodyCompletion::new(model, self);
-        client.post(format!("{base}/complete")).json(&body)
+        self.build_completion_request(client, base, model, None, false)
     }
 
     fn body_to_output(&self, mut response: Self::ResponseBody) -> Self::Output {
@@ -284,20 +649,35 @@ impl Task for TaskCompletion<'_> {
         } else {
             completion
         };
+        let completion = self
+            .stopping
+            .normalize_output(completion, finish_reason == "stop_sequence_reached");
+        let tokens = self
+            .tokens
+            .then(|| completion_tokens_with_ranges(&completion_tokens, &log_probs));
         CompletionOutput {
             completion,
             finish_reason,
+            model_version: response.model_version,
             logprobs: completion_logprobs_to_canonical(
                 log_probs,
                 completion_tokens,
                 self.logprobs.top_logprobs().unwrap_or_default(),
             ),
             usage: Usage {
-                prompt_tokens: response.num_tokens_prompt_total,
-                completion_tokens: response.num_tokens_generated,
+                prompt_tokens: response.num_tokens_prompt_total.unwrap_or_default(),
+                completion_tokens: response.num_tokens_generated.unwrap_or_default(),
             },
+            num_tokens_prompt_total: response.num_tokens_prompt_total,
+            num_tokens_generated: response.num_tokens_generated,
+            optimized_prompt: response.optimized_prompt,
+            completion_tokens: tokens,
         }
     }
+
+    fn maximum_tokens(&self) -> Option<u32> {
+        self.stopping.maximum_tokens
+    }
 }
 
 fn completion_logprobs_to_canonical(
@@ -343,6 +723,9 @@ pub struct StreamChunk {
     pub index: u32,
     /// The completion of the stream.
     pub completion: String,
+    /// Token usage of the stream so far, if the server reports it with every chunk.
+    #[serde(default)]
+    pub usage: Option<Usage>,
 }
 
 /// Denotes the end of a completion stream.
@@ -380,6 +763,22 @@ pub enum CompletionEvent {
     CompletionSummary(CompletionSummary),
 }
 
+impl CompletionEvent {
+    /// Token usage reported by this event. The running usage for chunks, if the server reports
+    /// it, and the total usage for the [`CompletionEvent::CompletionSummary`] at the end of the
+    /// stream.
+    pub fn usage(&self) -> Option<Usage> {
+        match self {
+            CompletionEvent::StreamChunk(chunk) => chunk.usage,
+            CompletionEvent::StreamSummary(_) => None,
+            CompletionEvent::CompletionSummary(summary) => Some(Usage {
+                prompt_tokens: summary.num_tokens_prompt_total,
+                completion_tokens: summary.num_tokens_generated,
+            }),
+        }
+    }
+}
+
 impl StreamTask for TaskCompletion<'_> {
     type Output = CompletionEvent;
 
@@ -391,13 +790,64 @@ impl StreamTask for TaskCompletion<'_> {
         base: &str,
         model: &str,
     ) -> reqwest::RequestBuilder {
-        let body = BodyCompletion::new(model, self).with_streaming();
-        client.post(format!("{base}/complete")).json(&body)
+        self.build_completion_request(client, base, model, None, true)
     }
 
     fn body_to_output(response: Self::ResponseBody) -> Self::Output {
         response
     }
+
+    fn maximum_tokens(&self) -> Option<u32> {
+        self.stopping.maximum_tokens
+    }
+}
+
+impl Task for WithSamplingDefaults<'_, TaskCompletion<'_>> {
+    type Output = CompletionOutput;
+
+    type ResponseBody = ResponseCompletion;
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        base: &str,
+        model: &str,
+    ) -> reqwest::RequestBuilder {
+        self.task
+            .build_completion_request(client, base, model, Some(self.defaults), false)
+    }
+
+    fn body_to_output(&self, response: Self::ResponseBody) -> Self::Output {
+        Task::body_to_output(self.task, response)
+    }
+
+    fn maximum_tokens(&self) -> Option<u32> {
+        self.task.stopping.maximum_tokens
+    }
+}
+
+impl StreamTask for WithSamplingDefaults<'_, TaskCompletion<'_>> {
+    type Output = CompletionEvent;
+
+    type ResponseBody = CompletionEvent;
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        base: &str,
+        model: &str,
+    ) -> reqwest::RequestBuilder {
+        self.task
+            .build_completion_request(client, base, model, Some(self.defaults), true)
+    }
+
+    fn body_to_output(response: Self::ResponseBody) -> Self::Output {
+        response
+    }
+
+    fn maximum_tokens(&self) -> Option<u32> {
+        self.task.stopping.maximum_tokens
+    }
 }
 
 impl Logprobs {
@@ -418,3 +868,241 @@ impl Logprobs {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_maximum_tokens_without_logprobs_is_reported() {
+        let task = TaskCompletion::from_text("Hello").with_maximum_tokens(0);
+
+        let warnings = task.validate().unwrap();
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].field, "maximum_tokens");
+    }
+
+    #[test]
+    fn zero_maximum_tokens_for_scoring_is_fine() {
+        let task = TaskCompletion::from_text("Hello")
+            .with_maximum_tokens(0)
+            .with_logprobs(Logprobs::Sampled);
+
+        assert!(task.validate().unwrap().is_empty());
+    }
+
+    #[test]
+    fn deterministic_task_can_be_overridden() {
+        let mut task = TaskCompletion::from_text("Hello").deterministic();
+        assert_eq!(task.sampling.temperature, Some(0.0));
+        assert_eq!(task.disable_optimizations, Some(true));
+
+        task.sampling.temperature = Some(0.5);
+
+        let body = serde_json::to_value(BodyCompletion::new("model", &task)).unwrap();
+        assert_eq!(body["temperature"], 0.5);
+        assert!(body.get("top_p").is_none());
+    }
+
+    #[test]
+    fn trailing_stop_sequence_is_stripped() {
+        let stopping = Stopping::from_stop_sequences(&["Question:"]);
+
+        let completion = stopping.normalize_output("Paris\nQuestion:".to_owned(), true);
+
+        assert_eq!(completion, "Paris\n");
+    }
+
+    #[test]
+    fn completion_without_stop_sequence_is_unchanged() {
+        let stopping = Stopping::from_stop_sequences(&["Question:"]);
+
+        let completion = stopping.normalize_output("Paris".to_owned(), false);
+
+        assert_eq!(completion, "Paris");
+    }
+
+    #[test]
+    fn stripped_stop_sequence_is_restored_if_requested() {
+        let stopping = Stopping::from_stop_sequences(&["Question:"]).with_stop_in_output();
+
+        let stripped = stopping.normalize_output("Paris\n".to_owned(), true);
+        let kept = stopping.normalize_output("Paris\nQuestion:".to_owned(), true);
+
+        assert_eq!(stripped, "Paris\nQuestion:");
+        assert_eq!(kept, "Paris\nQuestion:");
+    }
+
+    #[test]
+    fn ambiguous_stop_sequence_is_not_restored() {
+        let stopping = Stopping::from_stop_sequences(&["Question:", "\n\n"]).with_stop_in_output();
+
+        let completion = stopping.normalize_output("Paris".to_owned(), true);
+
+        assert_eq!(completion, "Paris");
+    }
+
+    #[test]
+    fn missing_token_counts_are_none() {
+        let response: ResponseCompletion = serde_json::from_str(
+            r#"{"model_version":"2021-12","completions":[{"completion":"\n","finish_reason":"maximum_tokens"}]}"#,
+        )
+        .unwrap();
+
+        let output = TaskCompletion::from_text("Hello").body_to_output(response);
+
+        assert_eq!(output.num_tokens_prompt_total, None);
+        assert_eq!(output.num_tokens_generated, None);
+        assert_eq!(output.usage.prompt_tokens, 0);
+    }
+
+    #[test]
+    fn generated_tokens_fall_back_to_number_of_completion_tokens() {
+        let response = |json: &str| serde_json::from_str::<ResponseCompletion>(json).unwrap();
+        let completions = r#""completions":[{"completion":" world!","finish_reason":"maximum_tokens","completion_tokens":[" world","!"]}]"#;
+        let with_count =
+            format!(r#"{{"model_version":"2021-12",{completions},"num_tokens_generated":7}}"#);
+        let without_count = format!(r#"{{"model_version":"2021-12",{completions}}}"#);
+        let with_tokens = TaskCompletion::from_text("Hello").with_tokens();
+        let without_tokens = TaskCompletion::from_text("Hello");
+
+        let reported = with_tokens.body_to_output(response(&with_count));
+        let counted = with_tokens.body_to_output(response(&without_count));
+        let unknown = without_tokens.body_to_output(response(&without_count));
+
+        assert_eq!(reported.generated_tokens(), Some(7));
+        assert_eq!(counted.generated_tokens(), Some(2));
+        assert_eq!(unknown.generated_tokens(), None);
+    }
+
+    #[test]
+    fn cloned_task_can_be_varied_independently() {
+        let base = TaskCompletion::from_text("An apple a day").with_maximum_tokens(10);
+
+        let mut variation = base.clone();
+        variation.sampling = Sampling::CREATIVE;
+
+        assert_eq!(base.sampling, Sampling::MOST_LIKELY);
+        assert_eq!(variation.prompt, base.prompt);
+        assert_ne!(variation, base);
+    }
+
+    #[test]
+    fn default_completion_caps_maximum_tokens() {
+        let task = TaskCompletion::default();
+
+        assert_eq!(task.stopping.maximum_tokens, Some(DEFAULT_MAXIMUM_TOKENS));
+    }
+
+    #[test]
+    fn field_order_of_completion_body_is_stable() {
+        let task = TaskCompletion {
+            sampling: Sampling {
+                temperature: Some(0.5),
+                top_k: Some(10),
+                top_p: Some(0.9),
+                frequency_penalty: Some(0.1),
+                presence_penalty: Some(0.2),
+            },
+            ..TaskCompletion::from_text("Hello")
+                .with_maximum_tokens(64)
+                .with_stop_sequences(&["\n"])
+                .with_special_tokens()
+                .with_logprobs(Logprobs::Top(2))
+                .with_optimizations_disabled()
+                .with_json_schema(serde_json::json!({"type": "string"}))
+        };
+
+        let body =
+            serde_json::to_string(&BodyCompletion::new("model", &task).with_streaming()).unwrap();
+
+        let expected = r#"{"model":"model","prompt":[{"type":"text","data":"Hello"}],"maximum_tokens":64,"stop_sequences":["\n"],"temperature":0.5,"top_k":10,"top_p":0.9,"stream":true,"raw_completion":true,"frequency_penalty":0.1,"presence_penalty":0.2,"log_probs":2,"tokens":true,"disable_optimizations":true,"guided_json":{"type":"string"}}"#;
+        assert_eq!(body, expected);
+    }
+
+    #[test]
+    fn streamed_and_non_streamed_completion_send_same_parameters() {
+        let mut task = TaskCompletion {
+            sampling: Sampling {
+                temperature: Some(0.5),
+                top_k: Some(10),
+                top_p: Some(0.9),
+                frequency_penalty: Some(0.1),
+                presence_penalty: Some(0.2),
+            },
+            ..TaskCompletion::from_text("Hello")
+                .with_maximum_tokens(64)
+                .with_stop_sequences(&["\n"])
+        };
+        task.extra
+            .insert("custom".to_owned(), serde_json::json!(42));
+        let client = reqwest::Client::new();
+        let body_of = |request: reqwest::RequestBuilder| {
+            let request = request.build().unwrap();
+            let body = request.body().unwrap().as_bytes().unwrap();
+            serde_json::from_slice::<serde_json::Value>(body).unwrap()
+        };
+
+        let mut plain = body_of(Task::build_request(&task, &client, "http://api", "model"));
+        let mut streamed = body_of(StreamTask::build_request(
+            &task,
+            &client,
+            "http://api",
+            "model",
+        ));
+
+        assert_eq!(streamed["stream"], serde_json::json!(true));
+        for body in [&mut plain, &mut streamed] {
+            body.as_object_mut().unwrap().remove("stream");
+        }
+        assert_eq!(plain, streamed);
+        assert_eq!(plain["custom"], serde_json::json!(42));
+    }
+
+    #[test]
+    fn penalties_at_the_boundaries_are_accepted() {
+        let task = TaskCompletion {
+            sampling: Sampling {
+                frequency_penalty: Some(-2.0),
+                presence_penalty: Some(2.0),
+                ..Sampling::MOST_LIKELY
+            },
+            ..TaskCompletion::from_text("Hello")
+        };
+
+        assert!(task.validate().is_ok());
+    }
+
+    #[test]
+    fn penalty_out_of_range_is_rejected() {
+        let task = TaskCompletion {
+            sampling: Sampling {
+                presence_penalty: Some(2.1),
+                ..Sampling::MOST_LIKELY
+            },
+            ..TaskCompletion::from_text("Hello")
+        };
+
+        let error = task.validate().unwrap_err();
+
+        assert!(
+            matches!(error, Error::InvalidParameter { field, .. } if field == "presence_penalty")
+        );
+    }
+
+    #[test]
+    fn usage_of_completion_events() {
+        let chunk: CompletionEvent = serde_json::from_str(
+            r#"{"type":"stream_chunk","index":0,"completion":" Hi","usage":{"prompt_tokens":3,"completion_tokens":1}}"#,
+        )
+        .unwrap();
+        let summary: CompletionEvent = serde_json::from_str(
+            r#"{"type":"completion_summary","num_tokens_prompt_total":3,"num_tokens_generated":2}"#,
+        )
+        .unwrap();
+
+        assert_eq!(chunk.usage().unwrap().completion_tokens, 1);
+        assert_eq!(summary.usage().unwrap().completion_tokens, 2);
+    }
+}
@@ -0,0 +1,171 @@
+use reqwest::Request;
+use serde_json::{json, Map, Value};
+
+use crate::Error;
+
+/// The flavour of HTTP API the client talks to. Set it with
+/// [`crate::ClientBuilder::with_dialect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ClientDialect {
+    /// The Aleph Alpha API. Completions are sent to `/complete`, chats to `/chat/completions`.
+    #[default]
+    AlephAlpha,
+    /// A gateway exposing the models with an OpenAI compatible API. Completions are sent to
+    /// `/v1/completions` and chats to `/v1/chat/completions`. The parameters of completion
+    /// requests are renamed accordingly, e.g. `maximum_tokens` is sent as `max_tokens`, and the
+    /// response is translated back. Parameters without an OpenAI counterpart, like
+    /// `disable_optimizations`, are omitted.
+    ///
+    /// Completions with [`crate::TaskCompletion::special_tokens`] and streamed completions fail
+    /// with [`Error::InvalidParameter`] before they are sent, since their responses can not be
+    /// translated. Other routes, e.g. for embeddings, are sent unchanged, as they have no common
+    /// OpenAI equivalent.
+    OpenAiCompatible,
+}
+
+/// Completion parameters which are named differently by OpenAI compatible APIs.
+const RENAMED_COMPLETION_FIELDS: [(&str, &str); 3] = [
+    ("maximum_tokens", "max_tokens"),
+    ("stop_sequences", "stop"),
+    ("log_probs", "logprobs"),
+];
+
+/// Completion parameters only understood by the Aleph Alpha API.
+const ALEPH_ALPHA_ONLY_COMPLETION_FIELDS: [&str; 3] =
+    ["raw_completion", "disable_optimizations", "tokens"];
+
+impl ClientDialect {
+    /// Rewrites path and body of a request built for the Aleph Alpha API.
+    pub(crate) fn adapt_request(self, request: &mut Request) -> Result<(), Error> {
+        if self == ClientDialect::AlephAlpha {
+            return Ok(());
+        }
+        let path = request.url().path().to_owned();
+        if let Some(prefix) = path.strip_suffix("/chat/completions") {
+            request
+                .url_mut()
+                .set_path(&format!("{prefix}/v1/chat/completions"));
+        } else if let Some(prefix) = path.strip_suffix("/complete") {
+            request
+                .url_mut()
+                .set_path(&format!("{prefix}/v1/completions"));
+            let Some(body) = request.body().and_then(|body| body.as_bytes()) else {
+                return Ok(());
+            };
+            let body = completion_body_to_openai(body)?;
+            *request.body_mut() = Some(body.into());
+        }
+        Ok(())
+    }
+
+    /// Translates the body of a response to a request rewritten by [`Self::adapt_request`] back
+    /// into the shape of the Aleph Alpha API. `path` is the path the request has been sent to.
+    pub(crate) fn adapt_response(self, path: &str, body: Vec<u8>) -> Result<Vec<u8>, Error> {
+        if self == ClientDialect::OpenAiCompatible && path.ends_with("/v1/completions") {
+            completion_response_from_openai(&body)
+        } else {
+            Ok(body)
+        }
+    }
+}
+
+fn completion_body_to_openai(body: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut body: Map<String, Value> =
+        serde_json::from_slice(body).expect("Request bodies are always valid JSON objects");
+    let unsupported = |field: &str| Error::InvalidParameter {
+        field: field.to_owned(),
+        message: "is not supported for OpenAI compatible APIs".to_owned(),
+    };
+    // OpenAI compatible APIs do not report the raw completion, and we do not translate the events
+    // of streams.
+    if body.get("raw_completion") == Some(&Value::Bool(true)) {
+        return Err(unsupported("special_tokens"));
+    }
+    if body.get("stream") == Some(&Value::Bool(true)) {
+        return Err(unsupported("stream"));
+    }
+    for (aleph_alpha, openai) in RENAMED_COMPLETION_FIELDS {
+        if let Some(value) = body.remove(aleph_alpha) {
+            body.insert(openai.to_owned(), value);
+        }
+    }
+    for field in ALEPH_ALPHA_ONLY_COMPLETION_FIELDS {
+        body.remove(field);
+    }
+    // The Aleph Alpha API expects a list of items, OpenAI compatible APIs a plain string.
+    let prompt = body.get("prompt").and_then(Value::as_array).map(|items| {
+        items
+            .iter()
+            .map(
+                |item| match (item["type"].as_str(), item["data"].as_str()) {
+                    (Some("text"), Some(text)) => Ok(text),
+                    _ => Err(Error::InvalidParameter {
+                        field: "prompt".to_owned(),
+                        message: "must only contain text for OpenAI compatible APIs".to_owned(),
+                    }),
+                },
+            )
+            .collect::<Result<String, Error>>()
+    });
+    if let Some(prompt) = prompt {
+        body.insert("prompt".to_owned(), Value::String(prompt?));
+    }
+    Ok(serde_json::to_vec(&body).expect("Serializing a JSON value must not fail"))
+}
+
+fn completion_response_from_openai(body: &[u8]) -> Result<Vec<u8>, Error> {
+    let invalid = |message: &str| Error::InvalidResponse {
+        deserialization_error: format!("OpenAI compatible completion response {message}"),
+    };
+    let body: Value = serde_json::from_slice(body).map_err(|e| invalid(&e.to_string()))?;
+    let completions: Vec<Value> = body["choices"]
+        .as_array()
+        .ok_or_else(|| invalid("is missing choices"))?
+        .iter()
+        .map(|choice| {
+            json!({
+                "completion": choice["text"],
+                "finish_reason": choice["finish_reason"],
+            })
+        })
+        .collect();
+    let translated = json!({
+        "model_version": body["model"].as_str().unwrap_or_default(),
+        "completions": completions,
+        "num_tokens_prompt_total": body["usage"]["prompt_tokens"],
+        "num_tokens_generated": body["usage"]["completion_tokens"],
+    });
+    Ok(serde_json::to_vec(&translated).expect("Serializing a JSON value must not fail"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn special_tokens_and_streams_are_rejected_for_openai() {
+        let special_tokens = br#"{"model":"m","prompt":"Hello","raw_completion":true}"#;
+        let stream = br#"{"model":"m","prompt":"Hello","stream":true}"#;
+
+        let special_tokens = completion_body_to_openai(special_tokens).unwrap_err();
+        let stream = completion_body_to_openai(stream).unwrap_err();
+
+        assert!(
+            matches!(special_tokens, Error::InvalidParameter { field, .. } if field == "special_tokens")
+        );
+        assert!(matches!(stream, Error::InvalidParameter { field, .. } if field == "stream"));
+    }
+
+    #[test]
+    fn completion_parameters_are_renamed_for_openai() {
+        let body = br#"{"model":"m","prompt":[{"type":"text","data":"Hello"}],"maximum_tokens":8,"stop_sequences":["\n"],"disable_optimizations":true}"#;
+
+        let body: Value =
+            serde_json::from_slice(&completion_body_to_openai(body).unwrap()).unwrap();
+
+        assert_eq!(
+            body,
+            json!({"model": "m", "prompt": "Hello", "max_tokens": 8, "stop": ["\n"]})
+        );
+    }
+}
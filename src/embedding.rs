@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Prompt, Task};
+
+/// How the embeddings of the individual tokens of a prompt are combined into a single vector.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Pooling {
+    /// Element-wise mean of all token embeddings. A good default.
+    Mean,
+    /// Element-wise maximum of all token embeddings.
+    Max,
+    /// Embedding of the last token, which has seen the entire prompt.
+    LastToken,
+}
+
+/// Input for a [crate::Client::embedding] request. Embeds a prompt with the hidden states of a
+/// single layer of the model, pooled into one vector. Unlike [`crate::TaskSemanticEmbedding`],
+/// these embeddings are not trained for similarity, but expose the raw representation of the
+/// model, e.g. for training your own classifier on top.
+///
+/// For every layer the model computes one vector per token, so the raw `/embed` route returns a
+/// nested structure of `layer -> pooling -> vector`. This task requests exactly one layer and one
+/// pooling, so the output is a single vector with as many dimensions as the hidden state of the
+/// model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskEmbedding<'a> {
+    /// The prompt (usually text) to be embedded.
+    pub prompt: Prompt<'a>,
+    /// Index of the layer the hidden states are taken from. `0` is the input embedding layer.
+    /// Negative values count from the end, so `-1` is the last layer.
+    pub layer: i32,
+    /// How the token embeddings are combined into a single vector.
+    pub pooling: Pooling,
+}
+
+impl<'a> TaskEmbedding<'a> {
+    /// Embed a text with the mean of the token embeddings of the last layer.
+    pub fn from_text(text: &'a str) -> Self {
+        Self {
+            prompt: Prompt::from_text(text),
+            layer: -1,
+            pooling: Pooling::Mean,
+        }
+    }
+
+    /// Take the hidden states from the given layer. See [`Self::layer`].
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Combine the token embeddings as specified. See [`Self::pooling`].
+    pub fn with_pooling(mut self, pooling: Pooling) -> Self {
+        self.pooling = pooling;
+        self
+    }
+}
+
+/// Body send to the Aleph Alpha API on the POST `/embed` route
+#[derive(Serialize, Debug)]
+struct BodyEmbedding<'a> {
+    model: &'a str,
+    prompt: Prompt<'a>,
+    layers: [i32; 1],
+    pooling: [Pooling; 1],
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ResponseEmbedding {
+    /// Pooled embeddings, keyed by layer (e.g. `layer_-1`) and pooling (e.g. `mean`).
+    embeddings: HashMap<String, HashMap<String, Vec<f32>>>,
+}
+
+/// Pooled embedding of a prompt, returned by an embedding task.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddingOutput {
+    pub embedding: Vec<f32>,
+}
+
+impl Task for TaskEmbedding<'_> {
+    type Output = EmbeddingOutput;
+    type ResponseBody = ResponseEmbedding;
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        base: &str,
+        model: &str,
+    ) -> reqwest::RequestBuilder {
+        let body = BodyEmbedding {
+            model,
+            prompt: self.prompt.borrow(),
+            layers: [self.layer],
+            pooling: [self.pooling],
+        };
+        client.post(format!("{base}/embed")).json(&body)
+    }
+
+    fn body_to_output(&self, response: Self::ResponseBody) -> Self::Output {
+        // We request exactly one layer with exactly one pooling, so there is exactly one vector.
+        let embedding = response
+            .embeddings
+            .into_values()
+            .flat_map(HashMap::into_values)
+            .next()
+            .expect("There must always be one pooled embedding");
+        EmbeddingOutput { embedding }
+    }
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Prompt, Task};
+
+/// Input for a [crate::Client::evaluation] request. Scores how likely the model considers an
+/// expected completion, given a prompt.
+pub struct TaskEvaluate<'a> {
+    /// The prompt (usually text) the expected completion is conditioned on.
+    pub prompt: Prompt<'a>,
+    /// The completion you would expect the model to produce. It is not generated, but scored.
+    pub completion_expected: &'a str,
+}
+
+impl<'a> TaskEvaluate<'a> {
+    /// Evaluate `completion_expected` as a continuation of a text prompt.
+    pub fn from_text(prompt: &'a str, completion_expected: &'a str) -> Self {
+        TaskEvaluate {
+            prompt: Prompt::from_text(prompt),
+            completion_expected,
+        }
+    }
+}
+
+/// Body send to the Aleph Alpha API on the POST `/evaluate` route
+#[derive(Serialize, Debug)]
+struct BodyEvaluate<'a> {
+    /// Name of the model tasked with evaluating the completion. E.g. `luminous-base`.
+    pub model: &'a str,
+    /// Prompt the expected completion is conditioned on.
+    pub prompt: Prompt<'a>,
+    /// The completion which is scored.
+    pub completion_expected: &'a str,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ResponseEvaluate {
+    result: EvaluateOutput,
+}
+
+/// Scores of an expected completion, returned by an evaluate task. All scores are optional, since
+/// the API may omit them, e.g. for an empty completion.
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+pub struct EvaluateOutput {
+    /// Log probability of producing the expected completion given the prompt. Higher is better.
+    /// This is the metric to use if you want to rank candidate completions against each other.
+    pub log_probability: Option<f64>,
+    /// Log perplexity associated with the expected completion given the prompt. Lower is better.
+    pub log_perplexity: Option<f64>,
+    /// Log perplexity associated with the expected completion, normalized by its number of tokens.
+    pub log_perplexity_per_token: Option<f64>,
+    /// Log perplexity associated with the expected completion, normalized by its number of
+    /// characters.
+    pub log_perplexity_per_character: Option<f64>,
+    /// Whether the expected completion would have been produced by greedy sampling.
+    pub correct_greedy: Option<bool>,
+    /// Number of tokens of the expected completion.
+    pub token_count: Option<u32>,
+    /// Number of characters of the expected completion.
+    pub character_count: Option<u32>,
+    /// Argmax completion given the input consisting of prompt and expected completion.
+    pub completion: Option<String>,
+}
+
+impl Task for TaskEvaluate<'_> {
+    type Output = EvaluateOutput;
+    type ResponseBody = ResponseEvaluate;
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        base: &str,
+        model: &str,
+    ) -> reqwest::RequestBuilder {
+        let body = BodyEvaluate {
+            model,
+            prompt: self.prompt.borrow(),
+            completion_expected: self.completion_expected,
+        };
+        client.post(format!("{base}/evaluate")).json(&body)
+    }
+
+    fn body_to_output(&self, response: Self::ResponseBody) -> Self::Output {
+        response.result
+    }
+}
@@ -0,0 +1,55 @@
+use serde::{ser::Error as _, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+/// Serializes `body` as a single flat object, with the entries of `extra` merged in. Entries whose
+/// key is already part of the serialized body are skipped, so the typed parameters always take
+/// precedence and the resulting JSON never contains a key twice.
+pub(crate) struct WithExtra<'a, B> {
+    body: &'a B,
+    extra: &'a Map<String, Value>,
+}
+
+impl<'a, B> WithExtra<'a, B> {
+    pub fn new(body: &'a B, extra: &'a Map<String, Value>) -> Self {
+        Self { body, extra }
+    }
+}
+
+impl<B: Serialize> Serialize for WithExtra<'_, B> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.extra.is_empty() {
+            return self.body.serialize(serializer);
+        }
+        let mut merged = match serde_json::to_value(self.body).map_err(S::Error::custom)? {
+            Value::Object(map) => map,
+            _ => return Err(S::Error::custom("request body must be a JSON object")),
+        };
+        for (key, value) in self.extra {
+            if !merged.contains_key(key) {
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+        merged.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn typed_and_extra_fields_are_merged_into_one_object() {
+        let body = json!({"model": "luminous-base", "temperature": 0.5});
+        let extra = json!({"disable_safety": true, "temperature": 2.0});
+        let extra = extra.as_object().unwrap();
+
+        let merged = serde_json::to_value(WithExtra::new(&body, extra)).unwrap();
+
+        assert_eq!(
+            merged,
+            json!({"model": "luminous-base", "temperature": 0.5, "disable_safety": true})
+        );
+    }
+}
@@ -1,12 +1,25 @@
-use std::{borrow::Cow, pin::Pin, time::Duration};
-
+use std::{
+    borrow::Cow,
+    fmt,
+    io::Write,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use futures_timer::Delay;
 use futures_util::{stream::StreamExt, Stream};
-use reqwest::{header, ClientBuilder, RequestBuilder, Response, StatusCode};
+use reqwest::{header, Request, RequestBuilder, Response, StatusCode};
 use serde::Deserialize;
 use thiserror::Error as ThisError;
 use tokenizers::Tokenizer;
 
-use crate::{How, StreamJob};
+#[cfg(feature = "cassette")]
+use crate::Cassette;
+use crate::{
+    cancellation::cancellable, sse::SseParser, ClientDialect, How, RetryPolicy, StreamJob,
+};
 use async_stream::stream;
 
 /// A job send to the Aleph Alpha Api using the http client. A job wraps all the knowledge required
@@ -29,6 +42,12 @@ pub trait Job {
 
     /// Parses the response of the server into higher level structs for the user.
     fn body_to_output(&self, response: Self::ResponseBody) -> Self::Output;
+
+    /// Upper bound of tokens the model generates for this job, if any. Scales the timeout, see
+    /// [`crate::ClientBuilder::with_timeout_per_token`].
+    fn maximum_tokens(&self) -> Option<u32> {
+        None
+    }
 }
 
 /// A task send to the Aleph Alpha Api using the http client. Requires to specify a model before it
@@ -47,6 +66,12 @@ pub trait Task {
     /// Parses the response of the server into higher level structs for the user.
     fn body_to_output(&self, response: Self::ResponseBody) -> Self::Output;
 
+    /// Upper bound of tokens the model generates for this job, if any. Scales the timeout, see
+    /// [`crate::ClientBuilder::with_timeout_per_token`].
+    fn maximum_tokens(&self) -> Option<u32> {
+        None
+    }
+
     /// Turn your task into [`Job`] by annotating it with a model name.
     fn with_model<'a>(&'a self, model: &'a str) -> MethodJob<'a, Self>
     where
@@ -80,6 +105,10 @@ where
     fn body_to_output(&self, response: T::ResponseBody) -> T::Output {
         self.task.body_to_output(response)
     }
+
+    fn maximum_tokens(&self) -> Option<u32> {
+        self.task.maximum_tokens()
+    }
 }
 
 /// Sends HTTP request to the Aleph Alpha API
@@ -87,23 +116,180 @@ pub struct HttpClient {
     base: String,
     http: reqwest::Client,
     api_token: Option<String>,
+    /// Minimum size in bytes of a request body to be compressed with gzip. `None` disables
+    /// compression.
+    request_compression: Option<usize>,
+    retry_policy: RetryPolicy,
+    auth_header: AuthHeader,
+    observer: Option<Observer>,
+    /// Reading a response body is aborted once it exceeds this many bytes.
+    max_response_bytes: usize,
+    /// Flavour of the API requests are translated to.
+    dialect: ClientDialect,
+    /// Appended to the query of every request, e.g. `api-version` for some gateways.
+    query: Vec<(String, String)>,
+    /// Added to the timeout once for each token a task may generate. `None` keeps the timeout
+    /// fixed.
+    timeout_per_token: Option<Duration>,
+    /// Records or replays all requests, instead of just sending them.
+    #[cfg(feature = "cassette")]
+    cassette: Option<Cassette>,
 }
 
-impl HttpClient {
-    /// In production you typically would want set this to <https://inference-api.pharia.your-company.com>.
-    /// Yet you may want to use a different instance for testing.
-    pub fn new(host: String, api_token: Option<String>) -> Result<Self, Error> {
-        let http = ClientBuilder::new().build()?;
+/// Default for the maximum size of response bodies. Large enough for big batches of embeddings or
+/// tokenizers, but prevents a misbehaving server from exhausting memory.
+pub(crate) const DEFAULT_MAX_RESPONSE_BYTES: usize = 128 * 1024 * 1024;
+
+/// Callback invoked with the [`CallMetrics`] of every successful call.
+pub(crate) type Observer = Arc<dyn Fn(&CallMetrics) + Send + Sync>;
+
+/// Header the API token is sent in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AuthHeader {
+    pub name: header::HeaderName,
+    /// Prepended to the token, separated by a space. E.g. `Bearer`.
+    pub scheme: Option<String>,
+}
+
+impl AuthHeader {
+    /// Value of the header carrying `api_token`. Fails if the token contains bytes which are not
+    /// allowed in a header value, e.g. a trailing newline copied along with the token.
+    pub fn value(&self, api_token: &str) -> Result<header::HeaderValue, Error> {
+        let value = match &self.scheme {
+            Some(scheme) => format!("{scheme} {api_token}"),
+            None => api_token.to_owned(),
+        };
+        let mut value =
+            header::HeaderValue::from_str(&value).map_err(|_| Error::InvalidParameter {
+                field: "api_token".to_owned(),
+                message: "contains characters which are not allowed in a header".to_owned(),
+            })?;
+        // Consider marking security-sensitive headers with `set_sensitive`.
+        value.set_sensitive(true);
+        Ok(value)
+    }
+}
 
-        Ok(Self {
+impl Default for AuthHeader {
+    fn default() -> Self {
+        Self {
+            name: header::AUTHORIZATION,
+            scheme: Some("Bearer".to_owned()),
+        }
+    }
+}
+
+/// Placeholder printed instead of API tokens, so they do not leak into logs.
+pub(crate) const REDACTED: &str = "<redacted>";
+
+impl fmt::Debug for HttpClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("HttpClient");
+        debug
+            .field("base", &self.base)
+            .field("api_token", &self.api_token.as_ref().map(|_| REDACTED))
+            .field("request_compression", &self.request_compression)
+            .field("retry_policy", &self.retry_policy)
+            .field("auth_header", &self.auth_header)
+            .field("observer", &self.observer.as_ref().map(|_| "<observer>"))
+            .field("max_response_bytes", &self.max_response_bytes)
+            .field("dialect", &self.dialect)
+            .field("query", &self.query)
+            .field("timeout_per_token", &self.timeout_per_token);
+        #[cfg(feature = "cassette")]
+        debug.field("cassette", &self.cassette);
+        debug.finish()
+    }
+}
+
+impl HttpClient {
+    /// Send requests using an already configured `reqwest` client.
+    pub fn with_reqwest_client(
+        host: String,
+        http: reqwest::Client,
+        api_token: Option<String>,
+    ) -> Self {
+        Self {
             base: host,
             http,
             api_token,
-        })
+            request_compression: None,
+            retry_policy: RetryPolicy::NONE,
+            auth_header: AuthHeader::default(),
+            observer: None,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            dialect: ClientDialect::default(),
+            query: Vec::new(),
+            timeout_per_token: None,
+            #[cfg(feature = "cassette")]
+            cassette: None,
+        }
+    }
+
+    pub fn with_timeout_per_token(mut self, timeout_per_token: Option<Duration>) -> Self {
+        self.timeout_per_token = timeout_per_token;
+        self
+    }
+
+    /// Timeout of a request generating at most `maximum_tokens`, i.e. `how.client_timeout` plus
+    /// the timeout per token for each of them.
+    fn timeout(&self, how: &How, maximum_tokens: Option<u32>) -> Duration {
+        match (self.timeout_per_token, maximum_tokens) {
+            (Some(per_token), Some(maximum_tokens)) => how
+                .client_timeout
+                .saturating_add(per_token.saturating_mul(maximum_tokens)),
+            _ => how.client_timeout,
+        }
+    }
+
+    pub fn with_query(mut self, query: Vec<(String, String)>) -> Self {
+        self.query = query;
+        self
+    }
+
+    #[cfg(feature = "cassette")]
+    pub fn with_cassette(mut self, cassette: Option<Cassette>) -> Self {
+        self.cassette = cassette;
+        self
+    }
+
+    pub fn with_dialect(mut self, dialect: ClientDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn with_max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = max_response_bytes;
+        self
+    }
+
+    pub fn with_observer(mut self, observer: Option<Observer>) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    pub fn with_auth_header(mut self, auth_header: AuthHeader) -> Self {
+        self.auth_header = auth_header;
+        self
+    }
+
+    pub fn with_request_compression(mut self, min_body_size: Option<usize>) -> Self {
+        self.request_compression = min_body_size;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
     }
 
     /// Construct and execute a request building on top of a `RequestBuilder`
-    async fn response(&self, builder: RequestBuilder, how: &How) -> Result<Response, Error> {
+    async fn response(
+        &self,
+        builder: RequestBuilder,
+        how: &How,
+        timeout: Duration,
+    ) -> Result<(Response, PendingCall), Error> {
         let query = if how.be_nice {
             [("nice", "true")].as_slice()
         } else {
@@ -116,20 +302,70 @@ impl HttpClient {
             .as_ref()
             .or(self.api_token.as_ref())
             .expect("API token needs to be set on client construction or per request");
-        let response = builder
+        let mut builder = builder
             .query(query)
-            .header(header::AUTHORIZATION, Self::header_from_token(api_token))
-            .timeout(how.client_timeout)
-            .send()
-            .await
-            .map_err(|reqwest_error| {
-                if reqwest_error.is_timeout() {
-                    Error::ClientTimeout(how.client_timeout)
-                } else {
-                    reqwest_error.into()
-                }
-            })?;
-        translate_http_error(response).await
+            .query(&self.query)
+            .header(&self.auth_header.name, self.auth_header.value(api_token)?)
+            .timeout(timeout);
+        if let Some(idempotency_key) = &how.idempotency_key {
+            builder = builder.header("Idempotency-Key", idempotency_key);
+        }
+        let mut request = builder.build()?;
+        self.dialect.adapt_request(&mut request)?;
+        if let Some(min_body_size) = self.request_compression {
+            compress_body(&mut request, min_body_size);
+        }
+
+        let started = Instant::now();
+        let call = PendingCall {
+            path: request.url().path().to_owned(),
+            request_body_bytes: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map_or(0, <[u8]>::len),
+            started,
+        };
+        let mut retry = 0;
+        loop {
+            // Bodies are always buffered in memory, so the request can be cloned. Should this
+            // ever change, we just do not retry.
+            let Some(next_attempt) = request.try_clone() else {
+                let response = self.execute(request, timeout).await?;
+                return Ok((response, call));
+            };
+            let error = match self.execute(request, timeout).await {
+                Ok(response) => return Ok((response, call)),
+                Err(error) => error,
+            };
+            let Some(backoff) = self.retry_policy.backoff(retry, started.elapsed(), &error) else {
+                return Err(error);
+            };
+            Delay::new(backoff).await;
+            request = next_attempt;
+            retry += 1;
+        }
+    }
+
+    /// Send the request once, without retries.
+    async fn execute(&self, request: Request, timeout: Duration) -> Result<Response, Error> {
+        let response = self.send(request).await.map_err(|error| match error {
+            Error::Other(reqwest_error) if reqwest_error.is_timeout() => {
+                Error::ClientTimeout(timeout)
+            }
+            error => error,
+        })?;
+        translate_http_error(response, self.max_response_bytes).await
+    }
+
+    /// Hands the request to the transport, i.e. the `reqwest` client or the cassette.
+    async fn send(&self, request: Request) -> Result<Response, Error> {
+        #[cfg(feature = "cassette")]
+        if let Some(cassette) = &self.cassette {
+            return cassette
+                .send(&self.http, request, self.max_response_bytes)
+                .await;
+        }
+        Ok(self.http.execute(request).await?)
     }
 
     /// Execute a task with the aleph alpha API and fetch its result.
@@ -158,11 +394,36 @@ impl HttpClient {
     /// }
     /// ```
     pub async fn output_of<T: Job>(&self, task: &T, how: &How) -> Result<T::Output, Error> {
+        let (answer, _meta) = self.output_with_meta_of(task, how).await?;
+        Ok(answer)
+    }
+
+    /// Same as [`Self::output_of`], but also returns metadata about the HTTP response.
+    pub async fn output_with_meta_of<T: Job>(
+        &self,
+        task: &T,
+        how: &How,
+    ) -> Result<(T::Output, ResponseMeta), Error> {
         let builder = task.build_request(&self.http, &self.base);
-        let response = self.response(builder, how).await?;
-        let response_body: T::ResponseBody = response.json().await?;
+        let timeout = self.timeout(how, task.maximum_tokens());
+        let (meta, bytes, call) = cancellable(how.cancellation.as_ref(), async {
+            let (response, call) = self.response(builder, how, timeout).await?;
+            let meta = ResponseMeta::from_response(&response);
+            let bytes = read_body(response, self.max_response_bytes).await?;
+            Ok((meta, bytes, call))
+        })
+        .await?;
+        let received_bytes = bytes.len();
+        let bytes = self.dialect.adapt_response(&call.path, bytes)?;
+        if let Some(observer) = &self.observer {
+            observer(&call.finish(meta.status, received_bytes));
+        }
+        let response_body: T::ResponseBody =
+            parse_response_body(bytes).map_err(|deserialization_error| Error::InvalidResponse {
+                deserialization_error,
+            })?;
         let answer = task.body_to_output(response_body);
-        Ok(answer)
+        Ok((answer, meta))
     }
 
     pub async fn stream_output_of<T: StreamJob>(
@@ -174,52 +435,85 @@ impl HttpClient {
         T::Output: 'static,
     {
         let builder = task.build_request(&self.http, &self.base);
-        let response = self.response(builder, how).await?;
+        let timeout = self.timeout(how, task.maximum_tokens());
+        let (response, call) = cancellable(
+            how.cancellation.as_ref(),
+            self.response(builder, how, timeout),
+        )
+        .await?;
+        let status = response.status();
         let mut stream = response.bytes_stream();
+        let mut parser = SseParser::default();
+        let observer = self.observer.clone();
+        let limit = self.max_response_bytes;
+        let cancellation = how.cancellation.clone();
 
         Ok(Box::pin(stream! {
-            while let Some(item) = stream.next().await {
+            let mut response_body_bytes = 0;
+            loop {
+                let item = match cancellable(cancellation.as_ref(), async { Ok(stream.next().await) }).await {
+                    Ok(Some(item)) => item,
+                    Ok(None) => break,
+                    Err(cancelled) => {
+                        yield Err(cancelled);
+                        return;
+                    }
+                };
                 match item {
                     Ok(bytes) => {
-                        let events = Self::parse_stream_event::<T::ResponseBody>(bytes.as_ref());
-                        for event in events {
-                            yield event.map(|b| T::body_to_output(b));
+                        response_body_bytes += bytes.len();
+                        if response_body_bytes > limit {
+                            yield Err(Error::ResponseTooLarge { limit });
+                            return;
+                        }
+                        for data in parser.push(bytes.as_ref()) {
+                            yield Self::parse_stream_data(&data).map(T::body_to_output);
                         }
                     }
                     Err(e) => {
                         yield Err(e.into());
                     }
                 }
+                // The last stream event for the chat endpoint (not for the completion endpoint)
+                // always is "[DONE]". There is no need to wait for the server to close the
+                // connection.
+                if parser.is_done() {
+                    break;
+                }
+            }
+            if let Some(observer) = observer {
+                observer(&call.finish(status, response_body_bytes));
+            }
+            for data in parser.finish() {
+                yield Self::parse_stream_data(&data).map(T::body_to_output);
             }
         }))
     }
 
-    /// Take a byte slice (of a SSE) and parse it into a provided response body.
-    /// Each SSE event is expected to contain one or multiple JSON bodies prefixed by `data: `.
-    fn parse_stream_event<StreamBody>(bytes: &[u8]) -> Vec<Result<StreamBody, Error>>
+    /// Parse the data of a single SSE event into the provided response body.
+    fn parse_stream_data<StreamBody>(data: &str) -> Result<StreamBody, Error>
     where
         StreamBody: for<'de> Deserialize<'de>,
     {
-        String::from_utf8_lossy(bytes)
-            .split("data: ")
-            .skip(1)
-            // The last stream event for the chat endpoint (not for the completion endpoint) always is "[DONE]"
-            // While we could model this as a variant of the `ChatStreamChunk` enum, the value of this is
-            // unclear, so we ignore it here.
-            .filter(|s| s.trim() != "[DONE]")
-            .map(|s| {
-                serde_json::from_str(s).map_err(|e| Error::InvalidStream {
-                    deserialization_error: e.to_string(),
-                })
-            })
-            .collect()
+        serde_json::from_str(data).map_err(|e| Error::InvalidStream {
+            deserialization_error: e.to_string(),
+        })
     }
 
-    fn header_from_token(api_token: &str) -> header::HeaderValue {
-        let mut auth_value = header::HeaderValue::from_str(&format!("Bearer {api_token}")).unwrap();
-        // Consider marking security-sensitive headers with `set_sensitive`.
-        auth_value.set_sensitive(true);
-        auth_value
+    /// Take a byte slice containing complete SSE events and parse each into a provided response
+    /// body.
+    #[cfg(test)]
+    fn parse_stream_event<StreamBody>(bytes: &[u8]) -> Vec<Result<StreamBody, Error>>
+    where
+        StreamBody: for<'de> Deserialize<'de>,
+    {
+        let mut parser = SseParser::default();
+        let mut events = parser.push(bytes);
+        events.extend(parser.finish());
+        events
+            .iter()
+            .map(|data| Self::parse_stream_data(data))
+            .collect()
     }
 
     pub async fn tokenizer_by_model(
@@ -234,11 +528,12 @@ impl HttpClient {
         let response = self
             .http
             .get(format!("{}/models/{model}/tokenizer", self.base))
-            .header(header::AUTHORIZATION, Self::header_from_token(api_token))
+            .query(&self.query)
+            .header(&self.auth_header.name, self.auth_header.value(api_token)?)
             .send()
             .await?;
-        let response = translate_http_error(response).await?;
-        let bytes = response.bytes().await?;
+        let response = translate_http_error(response, self.max_response_bytes).await?;
+        let bytes = read_body(response, self.max_response_bytes).await?;
         let tokenizer = Tokenizer::from_bytes(bytes).map_err(|e| Error::InvalidTokenizer {
             deserialization_error: e.to_string(),
         })?;
@@ -246,24 +541,193 @@ impl HttpClient {
     }
 }
 
-async fn translate_http_error(response: reqwest::Response) -> Result<reqwest::Response, Error> {
+/// Replaces the body of the request with its gzip compressed version, if it is at least
+/// `min_body_size` bytes large. Streamed bodies are left untouched.
+fn compress_body(request: &mut Request, min_body_size: usize) {
+    let Some(bytes) = request.body().and_then(|body| body.as_bytes()) else {
+        return;
+    };
+    if bytes.len() < min_body_size {
+        return;
+    }
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(bytes)
+        .expect("Writing into memory must not fail");
+    let compressed = encoder.finish().expect("Writing into memory must not fail");
+    *request.body_mut() = Some(compressed.into());
+    request.headers_mut().insert(
+        header::CONTENT_ENCODING,
+        header::HeaderValue::from_static("gzip"),
+    );
+}
+
+/// Measurements of a single successful call to the API, reported to the observer set with
+/// [`crate::ClientBuilder::with_observer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallMetrics {
+    /// Path of the route, e.g. `/complete`.
+    pub path: String,
+    /// Status code of the response.
+    pub status: StatusCode,
+    /// Size of the request body as sent, i.e. after compression. Measured on the already
+    /// serialized body, so no extra serialization is required.
+    pub request_body_bytes: usize,
+    /// Size of the response body as received by the client, i.e. after it has been decompressed.
+    pub response_body_bytes: usize,
+    /// Time from sending the request until the response body has been received completely,
+    /// including retries. For streams this is the time until the last event has been received.
+    pub latency: Duration,
+}
+
+/// A call whose response body has not been received completely yet.
+struct PendingCall {
+    path: String,
+    request_body_bytes: usize,
+    started: Instant,
+}
+
+impl PendingCall {
+    fn finish(self, status: StatusCode, response_body_bytes: usize) -> CallMetrics {
+        CallMetrics {
+            path: self.path,
+            status,
+            request_body_bytes: self.request_body_bytes,
+            response_body_bytes,
+            latency: self.started.elapsed(),
+        }
+    }
+}
+
+/// Metadata about a successful HTTP response of the Aleph Alpha API, which is not part of its body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseMeta {
+    /// Status code of the response. Any status code in the `2xx` range is considered a success.
+    pub status: StatusCode,
+    /// Whether the response has been served from a cache, as reported by the `X-Cache` header,
+    /// e.g. `HIT` or `MISS`. `None` if the header is absent or its value is not understood. You may
+    /// want to exclude cache hits from your own latency or usage metrics.
+    pub cache_hit: Option<bool>,
+}
+
+impl ResponseMeta {
+    fn from_response(response: &Response) -> Self {
+        Self {
+            status: response.status(),
+            cache_hit: response
+                .headers()
+                .get("x-cache")
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_cache_status),
+        }
+    }
+}
+
+/// Interprets the value of an `X-Cache` header. Proxies may append details, e.g.
+/// `HIT from proxy.your-company.com`, so only the first word is considered.
+fn parse_cache_status(value: &str) -> Option<bool> {
+    let status = value.split_whitespace().next()?;
+    if status.eq_ignore_ascii_case("hit") {
+        Some(true)
+    } else if status.eq_ignore_ascii_case("miss") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Deserializes the complete body of a response with `simd-json`.
+#[cfg(feature = "simd-json")]
+fn parse_response_body<T>(mut bytes: Vec<u8>) -> Result<T, String>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    simd_json::serde::from_slice(&mut bytes).map_err(|e| e.to_string())
+}
+
+/// Deserializes the complete body of a response with `serde_json`. Enable the `simd-json` feature
+/// to use `simd-json` instead.
+#[cfg(not(feature = "simd-json"))]
+fn parse_response_body<T>(bytes: Vec<u8>) -> Result<T, String>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    serde_json::from_slice(&bytes).map_err(|e| e.to_string())
+}
+
+/// Reads the entire body of the response, but fails with [`Error::ResponseTooLarge`] as soon as it
+/// exceeds `limit` bytes, so a misbehaving server can not exhaust our memory.
+pub(crate) async fn read_body(mut response: Response, limit: usize) -> Result<Vec<u8>, Error> {
+    let too_large = || Error::ResponseTooLarge { limit };
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit as u64)
+    {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Any status code in the `2xx` range is considered a success, every other status code is
+/// translated into an [`Error`].
+async fn translate_http_error(
+    response: reqwest::Response,
+    max_response_bytes: usize,
+) -> Result<reqwest::Response, Error> {
     let status = response.status();
     if !status.is_success() {
         // Store body in a variable, so we can use it, even if it is not an Error emitted by
         // the API, but an intermediate Proxy like NGinx, so we can still forward the error
         // message.
-        let body = response.text().await?;
+        let body = read_body(response, max_response_bytes).await?;
+        let body = String::from_utf8_lossy(&body).into_owned();
         // If the response is an error emitted by the API, this deserialization should succeed.
         let api_error: Result<ApiError, _> = serde_json::from_str(&body);
+        if let Ok(ApiError {
+            code,
+            prompt_tokens,
+            max_context_size,
+        }) = &api_error
+        {
+            if status == StatusCode::BAD_REQUEST && code == "INPUT_LENGTH_ERROR" {
+                return Err(Error::PromptTooLong {
+                    prompt_tokens: *prompt_tokens,
+                    max_context_size: *max_context_size,
+                });
+            }
+        }
         let translated_error = match status {
-            StatusCode::TOO_MANY_REQUESTS => Error::TooManyRequests,
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => {
+                match serde_json::from_str::<ParameterError>(&body) {
+                    Ok(ParameterError { param, message }) => Error::InvalidParameter {
+                        field: param,
+                        message,
+                    },
+                    Err(_) => Error::Http {
+                        status: status.as_u16(),
+                        body,
+                    },
+                }
+            }
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Error::Unauthorized {
+                status: status.as_u16(),
+                body,
+            },
+            StatusCode::TOO_MANY_REQUESTS => Error::TooManyRequests { body },
             StatusCode::SERVICE_UNAVAILABLE => {
                 // Presence of `api_error` implies the error originated from the API itself (rather
                 // than the intermediate proxy) and so we can decode it as such.
                 if api_error.is_ok_and(|error| error.code == "QUEUE_FULL") {
-                    Error::Busy
+                    Error::Busy { body }
                 } else {
-                    Error::Unavailable
+                    Error::Unavailable { body }
                 }
             }
             _ => Error::Http {
@@ -286,6 +750,21 @@ struct ApiError<'a> {
     /// E.g. Differentiating between request rate limiting and parallel tasks limiting which both
     /// are 429 (the former is emitted by NGinx though).
     code: Cow<'a, str>,
+    /// Number of tokens in the prompt. Reported alongside `INPUT_LENGTH_ERROR`, if known.
+    #[serde(default)]
+    prompt_tokens: Option<u32>,
+    /// Size of the context of the model. Reported alongside `INPUT_LENGTH_ERROR`, if known.
+    #[serde(default)]
+    max_context_size: Option<u32>,
+}
+
+/// Error body of a rejected request, which names the offending parameter. Emitted e.g. by OpenAI
+/// compatible routes for out of range sampling parameters.
+#[derive(Deserialize, Debug)]
+struct ParameterError {
+    param: String,
+    #[serde(alias = "error")]
+    message: String,
 }
 
 /// Errors returned by the Aleph Alpha Client
@@ -296,7 +775,11 @@ pub enum Error {
         "You are trying to send too many requests to the API in to short an interval. Slow down a \
         bit, otherwise these error will persist. Sorry for this, but we try to prevent DOS attacks."
     )]
-    TooManyRequests,
+    TooManyRequests {
+        /// Body of the response, for logging. May be plain text, e.g. if the request has been
+        /// rejected by a proxy.
+        body: String,
+    },
     /// Model is busy. Most likely due to many other users requesting its services right now.
     #[error(
         "Sorry the request to the Aleph Alpha API has been rejected due to the requested model \
@@ -304,15 +787,43 @@ pub enum Error {
         reasonable timeframe, so it was rejected right away, rather than make you wait. You are \
         welcome to retry your request any time."
     )]
-    Busy,
+    Busy {
+        /// Body of the response, for logging.
+        body: String,
+    },
     /// The API itself is unavailable, most likely due to restart.
     #[error(
         "The service is currently unavailable. This is likely due to restart. Please try again \
         later."
     )]
-    Unavailable,
+    Unavailable {
+        /// Body of the response, for logging. May be e.g. an HTML error page of a proxy.
+        body: String,
+    },
+    /// The API token is invalid, expired or lacks the permission for the request.
+    #[error("The API token has been rejected with status code {status}. Body:\n{body}")]
+    Unauthorized { status: u16, body: String },
     #[error("No response received within given timeout: {0:?}")]
     ClientTimeout(Duration),
+    /// The deadline passed to [`crate::Client::complete_before`] has passed, before any text has
+    /// been generated.
+    #[error("The deadline passed before any text has been generated.")]
+    DeadlineExceeded,
+    /// The prompt does not fit into the context of the model, leaving no room for the completion.
+    /// Either detected on the client side or reported by the API with the `INPUT_LENGTH_ERROR`
+    /// code. The number of tokens in the prompt and the size of the context are included, if
+    /// known, so you can truncate the prompt and try again.
+    #[error(
+        "The prompt is too long for the context of the model. Prompt tokens: {prompt_tokens:?}, \
+        context size: {max_context_size:?}"
+    )]
+    PromptTooLong {
+        prompt_tokens: Option<u32>,
+        max_context_size: Option<u32>,
+    },
+    /// A parameter of the request has been rejected, e.g. because it is out of range.
+    #[error("Invalid value for parameter '{field}': {message}")]
+    InvalidParameter { field: String, message: String },
     /// An error on the Http Protocol level.
     #[error("HTTP request failed with status code {}. Body:\n{}", status, body)]
     Http { status: u16, body: String },
@@ -321,12 +832,52 @@ pub enum Error {
         deserialization_error
     )]
     InvalidTokenizer { deserialization_error: String },
+    /// The response body exceeded the limit set with
+    /// [`crate::ClientBuilder::with_max_response_bytes`]. Reading it has been aborted.
+    #[error("The response body exceeded the limit of {limit} bytes.")]
+    ResponseTooLarge { limit: usize },
+    /// The body of a successful response could not be deserialized into the expected shape.
+    #[error(
+        "Response body could not be correctly deserialized. Caused by:\n{}.",
+        deserialization_error
+    )]
+    InvalidResponse { deserialization_error: String },
     /// Deserialization error of the stream event.
     #[error(
         "Stream event could not be correctly deserialized. Caused by:\n{}.",
         deserialization_error
     )]
     InvalidStream { deserialization_error: String },
+    /// The API does not list a model with the requested name as available.
+    #[error("Model '{model}' is not available.")]
+    ModelNotFound { model: String },
+    /// The content of a message could not be deserialized into the type requested by the user.
+    #[error("Message content could not be parsed as expected JSON. Content:\n{content}")]
+    ContentParse {
+        content: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// The request has been cancelled with the [`crate::CancellationToken`] passed in
+    /// [`crate::How::cancellation`]. Cancelled requests are never retried.
+    #[error("The request has been cancelled.")]
+    Cancelled,
+    /// Reading the text of a prompt from the source provided by the user failed, e.g. because it
+    /// is not valid UTF-8. See `Prompt::from_reader`, which requires the `tokio` feature.
+    #[error("Reading the prompt failed.")]
+    ReadPrompt(#[source] std::io::Error),
+    /// Reading or writing the file of a `Cassette` failed, or its content is invalid. Only returned
+    /// with the `cassette` feature.
+    #[error("Reading or writing the cassette failed.")]
+    Cassette(#[source] std::io::Error),
+    /// A `Cassette` has been asked to replay a request, which it has not recorded, or which has
+    /// already been replayed as often as it has been recorded. Only returned with the `cassette`
+    /// feature.
+    #[error("The cassette contains no (further) recorded response for {method} {path}.")]
+    UnrecordedRequest { method: String, path: String },
+    /// Writing the streamed content into the sink provided by the user failed.
+    #[error("Writing the streamed content failed.")]
+    Sink(#[source] std::io::Error),
     /// Most likely either TLS errors creating the Client, or IO errors.
     #[error(transparent)]
     Other(#[from] reqwest::Error),
@@ -338,6 +889,34 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn cache_status_is_parsed_from_first_word() {
+        assert_eq!(
+            parse_cache_status("HIT from proxy.your-company.com"),
+            Some(true)
+        );
+        assert_eq!(parse_cache_status("miss"), Some(false));
+        assert_eq!(parse_cache_status("REVALIDATED"), None);
+    }
+
+    #[test]
+    fn debug_output_redacts_api_token() {
+        // Given a client with an API token
+        let client = HttpClient::with_reqwest_client(
+            "https://inference-api.example.com".to_owned(),
+            reqwest::Client::new(),
+            Some("secret-token".to_owned()),
+        );
+
+        // When it is formatted for debugging
+        let debug = format!("{client:?}");
+
+        // Then the base URL is shown, but the token is not
+        assert!(debug.contains("https://inference-api.example.com"));
+        assert!(debug.contains(REDACTED));
+        assert!(!debug.contains("secret-token"));
+    }
+
     #[test]
     fn stream_chunk_event_is_parsed() {
         // Given some bytes
@@ -435,6 +1014,8 @@ mod tests {
         let event = events.first().unwrap().as_ref().unwrap();
 
         // Then the event is a chat stream chunk with a done event
-        assert!(matches!(&event.choices[0], ChatChunk::Finished { reason } if reason == "stop"));
+        assert!(
+            matches!(&event.choices[0], ChatChunk::Finished { reason, .. } if reason == "stop")
+        );
     }
 }
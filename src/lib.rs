@@ -22,57 +22,140 @@
 //!     println!("An apple a day{}", response.completion);
 //! }
 //! ```
+//!
+//! # Cargo features
+//!
+//! * `gzip` (enabled by default): Ask the API for gzip compressed responses via the
+//!   `Accept-Encoding` header and transparently decompress them.
+//! * `brotli` (enabled by default): Same as `gzip`, but for brotli compressed responses.
+//! * `tokio`: Integration with `tokio` types. Adds `Client::stream_completion_into` and
+//!   `Client::stream_chat_into`, which forward streams into bounded `tokio::sync::mpsc` channels,
+//!   and `Prompt::from_reader`, which reads a prompt from a `tokio::io::AsyncRead`.
+//!   This is the only feature which requires `tokio`, as these functions take `tokio` types as
+//!   arguments. They work on any runtime though, since neither channels nor readers need a
+//!   `tokio` reactor.
+//! * `simd-json`: Parse response bodies with `simd-json` instead of `serde_json`. Faster for large
+//!   responses, e.g. batches of embeddings. Runtime agnostic.
+//! * `cassette`: Record the HTTP interactions of a client to a file and replay them in tests, see
+//!   `Cassette`. Runtime agnostic.
+//! * `integration-tests`: Only relevant for developing this crate. Runs the tests in
+//!   `tests/live.rs` against the production API, if `AA_API_TOKEN` is set.
+//!
+//! # Async runtime
+//!
+//! The client itself does not depend on a specific async runtime. It never spawns tasks and does not
+//! use runtime specific timers or synchronization primitives. Concurrency, like in
+//! [`Client::evaluate_batch`], is achieved with runtime agnostic combinators from `futures`.
+//! Timeouts (see [`How::client_timeout`]) are enforced by `reqwest`. The backoff between retries
+//! and the deadline of [`Client::complete_before`] use `futures-timer`, and cancellation (see
+//! [`CancellationToken`]) uses `event-listener`, neither of which needs a runtime. Consequently only
+//! the `tokio` feature requires `tokio`, the examples and tests just happen to use it.
+//!
+//! Be aware though that `reqwest` performs its network I/O on a `tokio` reactor. If your
+//! application runs on `async-std` or `smol`, wrap the futures returned by the client with a
+//! compatibility layer like `async-compat`, which provides such a reactor in the background.
 
+mod cancellation;
+#[cfg(feature = "cassette")]
+mod cassette;
 mod chat;
+mod client_builder;
 mod completion;
 mod detokenization;
+mod dialect;
+mod embedding;
+mod evaluation;
 mod explanation;
+mod extra;
 mod http;
 mod image_preprocessing;
 mod logprobs;
+mod model;
+mod priority;
 mod prompt;
+mod raw;
+mod retry;
 mod semantic_embedding;
+mod sse;
 mod stream;
 mod tokenization;
+mod truncation;
+mod validation;
+use async_stream::try_stream;
+use chat::ChatStreamCollector;
+use completion::WithSamplingDefaults;
 use dotenvy::dotenv;
-use futures_util::Stream;
+use futures_timer::Delay;
+use futures_util::{
+    future::{select, BoxFuture, Either},
+    stream::iter,
+    Stream, StreamExt, TryStreamExt,
+};
 use http::HttpClient;
+use raw::RawJob;
 use semantic_embedding::{BatchSemanticEmbeddingOutput, SemanticEmbeddingOutput};
-use std::env;
+use std::{
+    env, fmt,
+    io::{self, Write},
+    pin::pin,
+};
 use std::{pin::Pin, time::Duration};
 use tokenizers::Tokenizer;
 
+#[cfg(feature = "cassette")]
+pub use self::cassette::Cassette;
 pub use self::{
+    cancellation::CancellationToken,
     chat::{
-        ChatChunk, ChatOutput, ChatSampling, Distribution, Message, StreamChatEvent, StreamMessage,
+        ChatChunk, ChatDelta, ChatImage, ChatOutput, ChatSampling, Citation, Distribution,
+        Document, FinishReason, Message, OwnedMessage, Role, StreamChatEvent, StreamMessage,
         TaskChat, Usage,
     },
+    client_builder::ClientBuilder,
     completion::{
-        CompletionEvent, CompletionOutput, CompletionSummary, Sampling, Stopping, StreamChunk,
-        StreamSummary, TaskCompletion,
+        CompletionEvent, CompletionOrder, CompletionOutput, CompletionSummary, CompletionToken,
+        Sampling, Stopping, StreamChunk, StreamSummary, TaskCompletion, DEFAULT_MAXIMUM_TOKENS,
     },
     detokenization::{DetokenizationOutput, TaskDetokenization},
+    dialect::ClientDialect,
+    embedding::{EmbeddingOutput, Pooling, TaskEmbedding},
+    evaluation::{EvaluateOutput, TaskEvaluate},
     explanation::{
         Explanation, ExplanationOutput, Granularity, ImageScore, ItemExplanation,
         PromptGranularity, TaskExplanation, TextScore,
     },
-    http::{Error, Job, Task},
+    http::{CallMetrics, Error, Job, ResponseMeta, Task},
     logprobs::{Logprob, Logprobs},
-    prompt::{Modality, Prompt},
+    model::{Model, ModelMetadata, ModelName, TaskModelsAvailable},
+    priority::Priority,
+    prompt::{FewShot, Modality, Prompt},
+    retry::RetryPolicy,
     semantic_embedding::{
-        SemanticRepresentation, TaskBatchSemanticEmbedding, TaskSemanticEmbedding,
+        chunk_by_tokens, SemanticRepresentation, TaskBatchSemanticEmbedding, TaskSemanticEmbedding,
+        DEFAULT_EMBEDDING_BATCH_SIZE,
     },
-    stream::{StreamJob, StreamTask},
+    stream::{MeasuredStream, StreamJob, StreamMetrics, StreamTask},
     tokenization::{TaskTokenization, TokenizationOutput},
+    truncation::TruncationStrategy,
+    validation::ValidationWarning,
 };
 
+/// Maximum number of requests [`Client::evaluate_batch`] keeps in flight at the same time.
+const MAX_CONCURRENT_EVALUATIONS: usize = 8;
+
+/// Maximum number of batches [`Client::embed_all`] keeps in flight at the same time.
+const MAX_CONCURRENT_EMBEDDING_BATCHES: usize = 4;
+
 /// Execute Jobs against the Aleph Alpha API
+#[derive(Debug)]
 pub struct Client {
     /// This client does all the work of sending the requests and talking to the AA API. The only
     /// additional knowledge added by this layer is that it knows about the individual jobs which
     /// can be executed, which allows for an alternative non generic interface which might produce
     /// easier to read code for the end user in many use cases.
     http_client: HttpClient,
+    /// Sampling parameters used for completions and chats which do not specify them.
+    default_sampling: Sampling,
 }
 
 impl Client {
@@ -84,8 +167,49 @@ impl Client {
     /// users. Having neither request, nor default authentication is considered a bug and will cause
     /// a panic.
     pub fn new(host: impl Into<String>, api_token: Option<String>) -> Result<Self, Error> {
-        let http_client = HttpClient::new(host.into(), api_token)?;
-        Ok(Self { http_client })
+        let builder = Self::builder(host);
+        match api_token {
+            Some(api_token) => builder.with_api_token(api_token),
+            None => builder,
+        }
+        .build()
+    }
+
+    /// Send all requests using an HTTP client you configured yourself. Most notably this allows
+    /// you to connect to on-premise deployments whose certificates are signed by a private
+    /// certificate authority, by adding its root certificate to the `reqwest` client. Settings of a
+    /// [`ClientBuilder`] which configure the HTTP client, e.g. the proxy, are not available in
+    /// combination with this constructor. Configure them on the `reqwest` client instead.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::Client;
+    ///
+    /// fn client_trusting_private_ca() -> Result<Client, Box<dyn std::error::Error>> {
+    ///     let pem = std::fs::read("my-company-root-ca.pem")?;
+    ///     let http = reqwest::Client::builder()
+    ///         .add_root_certificate(reqwest::Certificate::from_pem(&pem)?)
+    ///         .build()?;
+    ///     Ok(Client::with_base_url_and_client(
+    ///         "https://inference-api.pharia.your-company.com",
+    ///         Some("my-token".to_owned()),
+    ///         http,
+    ///     ))
+    /// }
+    /// ```
+    pub fn with_base_url_and_client(
+        host: impl Into<String>,
+        api_token: Option<String>,
+        http: reqwest::Client,
+    ) -> Self {
+        Client {
+            http_client: HttpClient::with_reqwest_client(host.into(), http, api_token),
+            default_sampling: Sampling::MOST_LIKELY,
+        }
+    }
+
+    /// Configure a client beyond host and API token. See [`ClientBuilder`].
+    pub fn builder(host: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(host)
     }
 
     /// A client instance that always uses the same token for all requests.
@@ -142,6 +266,16 @@ impl Client {
         self.http_client.output_of(task, how).await
     }
 
+    /// Same as [`Self::output_of`], but also returns metadata about the HTTP response, like its
+    /// status code.
+    pub async fn output_of_with_meta<T: Job>(
+        &self,
+        task: &T,
+        how: &How,
+    ) -> Result<(T::Output, ResponseMeta), Error> {
+        self.http_client.output_with_meta_of(task, how).await
+    }
+
     /// An embedding trying to capture the semantic meaning of a text. Cosine similarity can be used
     /// find out how well two texts (or multimodal prompts) match. Useful for search usecases.
     ///
@@ -154,6 +288,98 @@ impl Client {
         self.http_client.output_of(task, how).await
     }
 
+    /// Embed a search query, e.g. a question, to compare it against embeddings created with
+    /// [`Self::embed_document`].
+    ///
+    /// Query and document embeddings are asymmetric: each is optimized for its side of the
+    /// comparison, and they are only meaningful in combination with each other. Comparing a query
+    /// embedding against another query embedding, or against a symmetric one, yields poor
+    /// similarity scores without any error. Use these shortcuts to avoid mixing them up.
+    pub async fn embed_query(
+        &self,
+        text: &str,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<Vec<f32>, Error> {
+        self.embed(text, SemanticRepresentation::Query, model, how)
+            .await
+    }
+
+    /// Embed a document, e.g. a passage of your knowledge base, to compare it against embeddings
+    /// created with [`Self::embed_query`]. See there for why the representations must match.
+    pub async fn embed_document(
+        &self,
+        text: &str,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<Vec<f32>, Error> {
+        self.embed(text, SemanticRepresentation::Document, model, how)
+            .await
+    }
+
+    /// Embed a text to compare it against other embeddings created with this method, e.g. for
+    /// clustering or deduplication.
+    pub async fn embed_symmetric(
+        &self,
+        text: &str,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<Vec<f32>, Error> {
+        self.embed(text, SemanticRepresentation::Symmetric, model, how)
+            .await
+    }
+
+    async fn embed(
+        &self,
+        text: &str,
+        representation: SemanticRepresentation,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<Vec<f32>, Error> {
+        let model = model.into();
+        let task = TaskSemanticEmbedding {
+            prompt: Prompt::from_text(text),
+            representation,
+            compress_to_size: None,
+            normalize: false,
+        };
+        let output = self
+            .http_client
+            .output_of(&Task::with_model(&task, model.checked()?), how)
+            .await?;
+        Ok(output.embedding)
+    }
+
+    /// Embed a prompt with the pooled hidden states of one layer of the model. See
+    /// [`TaskEmbedding`] for how these differ from semantic embeddings.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, How, Error, Pooling, TaskEmbedding};
+    ///
+    /// async fn print_embedding() -> Result<(), Error> {
+    ///     let client = Client::from_env()?;
+    ///     let task = TaskEmbedding::from_text("An apple a day")
+    ///         .with_layer(-2)
+    ///         .with_pooling(Pooling::Max);
+    ///
+    ///     let output = client.embedding(&task, "luminous-base", &How::default()).await?;
+    ///
+    ///     println!("{:?}", output.embedding);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn embedding(
+        &self,
+        task: &TaskEmbedding<'_>,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<EmbeddingOutput, Error> {
+        let model = model.into();
+        self.http_client
+            .output_of(&Task::with_model(task, model.checked()?), how)
+            .await
+    }
+
     /// An batch of embeddings trying to capture the semantic meaning of a text.
     pub async fn batch_semantic_embedding(
         &self,
@@ -163,6 +389,81 @@ impl Client {
         self.http_client.output_of(task, how).await
     }
 
+    /// Semantic embeddings for any number of texts, in the same order as the texts. The texts are
+    /// split into batches of [`DEFAULT_EMBEDDING_BATCH_SIZE`], the most the API accepts in a single
+    /// request. Use [`Self::embed_all_in_batches_of`] to choose a different size. Up to four
+    /// batches are in flight at the same time. If any batch fails, the error of the first failing
+    /// batch is returned.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, Error, How, SemanticRepresentation};
+    ///
+    /// async fn index(documents: &[&str]) -> Result<Vec<Vec<f32>>, Error> {
+    ///     let client = Client::from_env()?;
+    ///     client
+    ///         .embed_all(
+    ///             documents,
+    ///             SemanticRepresentation::Document,
+    ///             "luminous-base",
+    ///             &How::default(),
+    ///         )
+    ///         .await
+    /// }
+    /// ```
+    pub async fn embed_all(
+        &self,
+        texts: &[&str],
+        representation: SemanticRepresentation,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<Vec<Vec<f32>>, Error> {
+        self.embed_all_in_batches_of(
+            DEFAULT_EMBEDDING_BATCH_SIZE,
+            texts,
+            representation,
+            model,
+            how,
+        )
+        .await
+    }
+
+    /// Like [`Self::embed_all`], but sends at most `batch_size` texts per request. Useful if your
+    /// deployment accepts fewer (or more) prompts per batch than the API.
+    ///
+    /// # Panics
+    ///
+    /// If `batch_size` is `0`.
+    pub async fn embed_all_in_batches_of(
+        &self,
+        batch_size: usize,
+        texts: &[&str],
+        representation: SemanticRepresentation,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<Vec<Vec<f32>>, Error> {
+        assert!(batch_size > 0, "batch_size must be at least 1");
+        let model = model.into();
+        let model = model.checked()?;
+        let batches: Vec<Vec<Vec<f32>>> = iter(texts.chunks(batch_size))
+            .map(|batch| async move {
+                let task = TaskBatchSemanticEmbedding {
+                    prompts: batch.iter().copied().map(Prompt::from_text).collect(),
+                    representation,
+                    compress_to_size: None,
+                    normalize: false,
+                };
+                let output = self
+                    .http_client
+                    .output_of(&Task::with_model(&task, model), how)
+                    .await?;
+                Ok::<_, Error>(output.embeddings)
+            })
+            .buffered(MAX_CONCURRENT_EMBEDDING_BATCHES)
+            .try_collect()
+            .await?;
+        Ok(batches.into_iter().flatten().collect())
+    }
+
     /// Instruct a model served by the aleph alpha API to continue writing a piece of text (or
     /// multimodal document).
     ///
@@ -192,14 +493,73 @@ impl Client {
     pub async fn completion(
         &self,
         task: &TaskCompletion<'_>,
-        model: &str,
+        model: impl Into<ModelName<'_>>,
         how: &How,
     ) -> Result<CompletionOutput, Error> {
+        let model = model.into();
+        let task = self.with_sampling_defaults(task);
         self.http_client
-            .output_of(&Task::with_model(task, model), how)
+            .output_of(&Task::with_model(&task, model.checked()?), how)
             .await
     }
 
+    /// Like [`Self::completion`], but takes ownership of the task. Convenient in async code, e.g.
+    /// if the task is built from data which does not outlive the current scope, or the future is
+    /// spawned. Prefer [`Self::completion`] if you want to reuse the task.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, Error, How, Prompt, TaskCompletion};
+    ///
+    /// async fn complete_in_background(client: Client, text: String) -> Result<String, Error> {
+    ///     let task = TaskCompletion {
+    ///         prompt: Prompt::from_text(text),
+    ///         ..TaskCompletion::from_text("")
+    ///     };
+    ///     let output = tokio::spawn(async move {
+    ///         client.completion_owned(task, "luminous-base", &How::default()).await
+    ///     })
+    ///     .await
+    ///     .unwrap()?;
+    ///     Ok(output.completion)
+    /// }
+    /// ```
+    pub async fn completion_owned(
+        &self,
+        task: TaskCompletion<'static>,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<CompletionOutput, Error> {
+        self.completion(&task, model, how).await
+    }
+
+    /// Shortcut for continuing a plain text prompt, generating at most `maximum_tokens`. Returns
+    /// only the generated text. Use [`Self::completion`] if you need control over sampling, stop
+    /// sequences or are interested in anything else but the text.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, How, Error};
+    ///
+    /// async fn print_completion() -> Result<(), Error> {
+    ///     let client = Client::from_env()?;
+    ///     let completion = client
+    ///         .complete_text("An apple a day", 64, "luminous-base", &How::default())
+    ///         .await?;
+    ///     println!("An apple a day{completion}");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn complete_text(
+        &self,
+        prompt: &str,
+        maximum_tokens: u32,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<String, Error> {
+        let task = TaskCompletion::from_text(prompt).with_maximum_tokens(maximum_tokens);
+        let output = self.completion(&task, model, how).await?;
+        Ok(output.completion)
+    }
+
     /// Instruct a model served by the aleph alpha API to continue writing a piece of text.
     /// Stream the response as a series of events.
     ///
@@ -232,14 +592,179 @@ impl Client {
     pub async fn stream_completion(
         &self,
         task: &TaskCompletion<'_>,
-        model: &str,
+        model: impl Into<ModelName<'_>>,
         how: &How,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<CompletionEvent, Error>> + Send>>, Error> {
+        let model = model.into();
+        let task = self.with_sampling_defaults(task);
         self.http_client
-            .stream_output_of(&Task::with_model(task, model), how)
+            .stream_output_of(&StreamTask::with_model(&task, model.checked()?), how)
             .await
     }
 
+    /// Complete each task of `tasks` as soon as it arrives, keeping at most `max_concurrency`
+    /// requests in flight. Yields the outputs in the given `order`. Unlike a batch, `tasks` may be
+    /// unbounded, e.g. fed from a queue by a long running worker. New tasks are only pulled from
+    /// `tasks` once a request slot is free, so a source producing faster than the API answers is
+    /// slowed down rather than buffered in memory.
+    ///
+    /// A failed task yields an error, but does not end the stream, so you can decide whether to
+    /// skip it or stop processing.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, CompletionOrder, How, TaskCompletion, Error};
+    /// use futures_util::{stream, StreamExt};
+    ///
+    /// async fn complete_all(client: &Client, prompts: Vec<String>) -> Result<(), Error> {
+    ///     let tasks = stream::iter(&prompts).map(|prompt| TaskCompletion::from_text(prompt));
+    ///     let how = How::default();
+    ///     let mut outputs =
+    ///         client.complete_stream(tasks, "luminous-base", &how, 4, CompletionOrder::AsSubmitted);
+    ///     while let Some(output) = outputs.next().await {
+    ///         println!("{}", output?.completion);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `max_concurrency` is `0`.
+    pub fn complete_stream<'a>(
+        &'a self,
+        tasks: impl Stream<Item = TaskCompletion<'a>> + Send + 'a,
+        model: impl Into<ModelName<'a>>,
+        how: &'a How,
+        max_concurrency: usize,
+        order: CompletionOrder,
+    ) -> impl Stream<Item = Result<CompletionOutput, Error>> + Send + 'a {
+        assert!(max_concurrency > 0, "max_concurrency must be at least 1");
+        let model = model.into();
+        let completions = tasks.map(move |task| {
+            let model = model.clone();
+            async move { self.completion(&task, model, how).await }
+        });
+        match order {
+            CompletionOrder::AsSubmitted => Either::Left(completions.buffered(max_concurrency)),
+            CompletionOrder::AsCompleted => {
+                Either::Right(completions.buffer_unordered(max_concurrency))
+            }
+        }
+    }
+
+    /// Stream a completion into `sender`. Returns once the stream has ended, or as soon as the
+    /// receiver has been dropped, in which case the request is cancelled. See
+    /// [`Self::stream_chat_into`] for the backpressure semantics.
+    #[cfg(feature = "tokio")]
+    pub async fn stream_completion_into(
+        &self,
+        task: &TaskCompletion<'_>,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+        sender: tokio::sync::mpsc::Sender<CompletionEvent>,
+    ) -> Result<(), Error> {
+        let stream = self.stream_completion(task, model, how).await?;
+        forward(stream, sender).await
+    }
+
+    /// Stream a completion and stop it early, as soon as `predicate` returns `true` for the text
+    /// accumulated so far. This allows for stop conditions beyond fixed stop sequences, e.g.
+    /// stopping once generated code has balanced brackets. Dropping the stream cancels the request,
+    /// so no more tokens are generated on the server. Returns the accumulated completion, including
+    /// the chunk which satisfied the predicate. If the predicate is never satisfied, the complete
+    /// completion is returned.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, How, TaskCompletion, Error};
+    ///
+    /// async fn complete_first_sentence() -> Result<(), Error> {
+    ///     let client = Client::from_env()?;
+    ///     let task = TaskCompletion::from_text("An apple a day");
+    ///
+    ///     let completion = client
+    ///         .stream_completion_until(&task, "luminous-base", &How::default(), |text| {
+    ///             text.contains('.')
+    ///         })
+    ///         .await?;
+    ///
+    ///     println!("An apple a day{completion}");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn stream_completion_until(
+        &self,
+        task: &TaskCompletion<'_>,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+        mut predicate: impl FnMut(&str) -> bool,
+    ) -> Result<String, Error> {
+        let mut stream = self.stream_completion(task, model, how).await?;
+        let mut completion = String::new();
+        while let Some(event) = stream.next().await {
+            if let CompletionEvent::StreamChunk(chunk) = event? {
+                completion.push_str(&chunk.completion);
+                if predicate(&completion) {
+                    break;
+                }
+            }
+        }
+        Ok(completion)
+    }
+
+    /// Stream a completion, but give up once `deadline` has passed. Returns the text generated up
+    /// to this point, so a request handler can answer with a partial completion rather than
+    /// exceed its latency budget. If the stream ends before the deadline, the complete completion
+    /// is returned.
+    ///
+    /// If the deadline passes before any text has been received, e.g. because the model is still
+    /// processing the prompt, this fails with [`Error::DeadlineExceeded`] instead of returning an
+    /// empty string, which would be indistinguishable from an empty completion. In either case the
+    /// stream is dropped, so the request is cancelled and no more tokens are generated.
+    ///
+    /// ```no_run
+    /// use std::time::{Duration, Instant};
+    /// use aleph_alpha_client::{Client, How, TaskCompletion, Error};
+    ///
+    /// async fn complete_within_two_seconds(client: &Client) -> Result<String, Error> {
+    ///     let task = TaskCompletion::from_text("An apple a day");
+    ///     let deadline = Instant::now() + Duration::from_secs(2);
+    ///     client
+    ///         .complete_before(&task, "luminous-base", &How::default(), deadline)
+    ///         .await
+    /// }
+    /// ```
+    pub async fn complete_before(
+        &self,
+        task: &TaskCompletion<'_>,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+        deadline: std::time::Instant,
+    ) -> Result<String, Error> {
+        let mut completion = String::new();
+        // `None` if the deadline passed first. The stream is dropped at the end of this block.
+        let streamed = {
+            let streaming = pin!(async {
+                let mut stream = self.stream_completion(task, model, how).await?;
+                while let Some(event) = stream.next().await {
+                    if let CompletionEvent::StreamChunk(chunk) = event? {
+                        completion.push_str(&chunk.completion);
+                    }
+                }
+                Ok(())
+            });
+            let timeout = Delay::new(deadline.saturating_duration_since(std::time::Instant::now()));
+            match select(streaming, timeout).await {
+                Either::Left((result, _timeout)) => Some(result),
+                Either::Right(((), _streaming)) => None,
+            }
+        };
+        match streamed {
+            Some(result) => result.map(|()| completion),
+            None if completion.is_empty() => Err(Error::DeadlineExceeded),
+            None => Ok(completion),
+        }
+    }
+
     /// Send a chat message to a model.
     /// ```no_run
     /// use aleph_alpha_client::{Client, How, TaskChat, Error, Message};
@@ -266,11 +791,55 @@ impl Client {
     pub async fn chat(
         &self,
         task: &TaskChat<'_>,
-        model: &str,
+        model: impl Into<ModelName<'_>>,
         how: &How,
     ) -> Result<ChatOutput, Error> {
+        let model = model.into();
+        let task = self.with_sampling_defaults(task);
         self.http_client
-            .output_of(&Task::with_model(task, model), how)
+            .output_of(&Task::with_model(&task, model.checked()?), how)
+            .await
+    }
+
+    /// Like [`Self::chat`], but takes ownership of the task. Convenient in async code, e.g. if the
+    /// conversation is assembled from owned strings (see [`OwnedMessage`]) and the future is
+    /// spawned. Prefer [`Self::chat`] if you want to reuse the task.
+    pub async fn chat_owned(
+        &self,
+        task: TaskChat<'static>,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<ChatOutput, Error> {
+        self.chat(&task, model, how).await
+    }
+
+    /// Send a conversation to a model, without building a [`TaskChat`]. The messages are sent as
+    /// they are, with the default sampling of the client (see
+    /// [`ClientBuilder::with_default_sampling`]) and no limit on the number of generated tokens.
+    /// Use [`Self::chat`] if you need more control.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, How, Error, Message};
+    ///
+    /// async fn print_chat(history: &[Message<'_>]) -> Result<(), Error> {
+    ///     let client = Client::from_env()?;
+    ///     let response = client
+    ///         .chat_messages(history, "pharia-1-llm-7b-control", &How::default())
+    ///         .await?;
+    ///     println!("{}", response.message.content);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn chat_messages(
+        &self,
+        messages: &[Message<'_>],
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<ChatOutput, Error> {
+        let model = model.into();
+        let task = self.with_sampling_defaults(&messages);
+        self.http_client
+            .output_of(&Task::with_model(&task, model.checked()?), how)
             .await
     }
 
@@ -303,14 +872,106 @@ impl Client {
     pub async fn stream_chat(
         &self,
         task: &TaskChat<'_>,
-        model: &str,
+        model: impl Into<ModelName<'_>>,
         how: &How,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChatEvent, Error>> + Send>>, Error> {
+        let model = model.into();
+        let task = self.with_sampling_defaults(task);
         self.http_client
-            .stream_output_of(&StreamTask::with_model(task, model), how)
+            .stream_output_of(&StreamTask::with_model(&task, model.checked()?), how)
             .await
     }
 
+    /// Send a chat message to a model, receive the answer as a stream, but return it as a single
+    /// [`ChatOutput`] once it is complete. Some deployments answer faster if the response is
+    /// streamed, because the server does not need to buffer it. This method gives you this
+    /// benefit without the need to handle the stream yourself. Note that the returned `logprobs`
+    /// are always empty, since they are not part of the stream.
+    pub async fn stream_chat_collect(
+        &self,
+        task: &TaskChat<'_>,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<ChatOutput, Error> {
+        self.stream_chat_to(task, model, how, io::sink()).await
+    }
+
+    /// Send a chat message to a model, receive the answer as a stream and concatenate its content
+    /// into a single string. Useful if you just want the text, but still benefit from streaming,
+    /// e.g. to avoid timeouts of intermediate proxies for long answers.
+    ///
+    /// The stream is consumed within the returned future, without spawning any tasks. This makes
+    /// the method cancellation safe: dropping the future, e.g. because it lost a `select!` or
+    /// exceeded a `tokio::time::timeout`, drops the response and closes the connection. No request
+    /// keeps running in the background.
+    pub async fn chat_stream_string(
+        &self,
+        task: &TaskChat<'_>,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<String, Error> {
+        let output = self.stream_chat_collect(task, model, how).await?;
+        Ok(output.message.content.into_owned())
+    }
+
+    /// Send a chat message to a model and write the content of the answer into `writer` while it
+    /// is streamed. The writer is flushed after each chunk, so e.g. a command line tool can
+    /// display the answer to the user as it is generated. Returns the complete answer together
+    /// with its token usage once the stream has ended.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, How, TaskChat, Error, Message};
+    ///
+    /// async fn print_chat_live() -> Result<(), Error> {
+    ///     let client = Client::from_env()?;
+    ///     let task = TaskChat::with_message(Message::user("Hello, how are you?"));
+    ///
+    ///     let output = client
+    ///         .stream_chat_to(&task, "pharia-1-llm-7b-control", &How::default(), std::io::stdout())
+    ///         .await?;
+    ///
+    ///     println!("\nTokens used: {}", output.usage.completion_tokens);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn stream_chat_to(
+        &self,
+        task: &TaskChat<'_>,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+        mut writer: impl Write,
+    ) -> Result<ChatOutput, Error> {
+        let mut stream = self.stream_chat(task, model, how).await?;
+        let mut collector = ChatStreamCollector::new(task);
+        while let Some(event) = stream.next().await {
+            if let Some(content) = collector.push(event?) {
+                writer.write_all(content.as_bytes()).map_err(Error::Sink)?;
+                writer.flush().map_err(Error::Sink)?;
+            }
+        }
+        collector.finish()
+    }
+
+    /// Stream a chat answer into `sender`. Returns once the stream has ended, or as soon as the
+    /// receiver has been dropped, in which case the request is cancelled.
+    ///
+    /// Each event is sent only after the previous one has been accepted by the channel. Once the
+    /// channel is full, reading from the HTTP response pauses until the receiver catches up, so at
+    /// most the capacity of the channel plus the small buffers of the HTTP connection are held in
+    /// memory. This allows you to apply backpressure, e.g. in a service aggregating many streams.
+    /// Errors are not sent through the channel, but end the stream and are returned.
+    #[cfg(feature = "tokio")]
+    pub async fn stream_chat_into(
+        &self,
+        task: &TaskChat<'_>,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+        sender: tokio::sync::mpsc::Sender<StreamChatEvent>,
+    ) -> Result<(), Error> {
+        let stream = self.stream_chat(task, model, how).await?;
+        forward(stream, sender).await
+    }
+
     /// Returns an explanation given a prompt and a target (typically generated
     /// by a previous completion request). The explanation describes how individual parts
     /// of the prompt influenced the target.
@@ -335,6 +996,11 @@ impl Client {
     ///         sampling: Sampling::MOST_LIKELY,
     ///         special_tokens: false,
     ///         logprobs: Logprobs::No,
+    ///         disable_optimizations: None,
+    ///         guided_json: None,
+    ///         tokens: false,
+    ///         extra: serde_json::Map::new(),
+    ///         priority: None,
     ///     };
     ///     let response = client.completion(&task, model, &How::default()).await?;
     ///
@@ -352,14 +1018,132 @@ impl Client {
     pub async fn explanation(
         &self,
         task: &TaskExplanation<'_>,
-        model: &str,
+        model: impl Into<ModelName<'_>>,
         how: &How,
     ) -> Result<ExplanationOutput, Error> {
         self.http_client
-            .output_of(&task.with_model(model), how)
+            .output_of(&task.with_model(model.into().as_str()), how)
             .await
     }
 
+    /// Score how likely the model considers an expected completion, given a prompt.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, Error, How, TaskEvaluate};
+    ///
+    /// async fn evaluate() -> Result<(), Error> {
+    ///     let client = Client::from_env()?;
+    ///
+    ///     let task = TaskEvaluate::from_text("An apple a day", " keeps the doctor away");
+    ///     let response = client.evaluation(&task, "luminous-base", &How::default()).await?;
+    ///
+    ///     dbg!(&response.log_probability);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn evaluation(
+        &self,
+        task: &TaskEvaluate<'_>,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<EvaluateOutput, Error> {
+        self.http_client
+            .output_of(&task.with_model(model.into().as_str()), how)
+            .await
+    }
+
+    /// Score several candidate completions for the same prompt, e.g. to rerank them. The
+    /// candidates are evaluated concurrently, with a bounded number of requests in flight. Scores
+    /// are returned in the same order as the candidates. Each candidate succeeds or fails on its
+    /// own, so a failing candidate does not cost you the scores of the others. Retry just the
+    /// failed ones, e.g. after [`Error::TooManyRequests`].
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, Error, How, Prompt};
+    ///
+    /// async fn rerank() -> Result<(), Error> {
+    ///     let client = Client::from_env()?;
+    ///
+    ///     let prompt = Prompt::from_text("The capital of France is");
+    ///     let candidates = [" Paris", " Berlin", " Rome"];
+    ///     let scores = client
+    ///         .evaluate_batch(&prompt, &candidates, "luminous-base", &How::default())
+    ///         .await;
+    ///
+    ///     for (candidate, score) in candidates.iter().zip(scores) {
+    ///         println!("{candidate}: {:?}", score?.log_probability);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn evaluate_batch(
+        &self,
+        prompt: &Prompt<'_>,
+        candidates: &[&str],
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Vec<Result<EvaluateOutput, Error>> {
+        let model = model.into();
+        let model = model.as_str();
+        iter(candidates)
+            .map(|completion_expected| async move {
+                let task = TaskEvaluate {
+                    prompt: prompt.borrow(),
+                    completion_expected,
+                };
+                self.evaluation(&task, model, how).await
+            })
+            .buffered(MAX_CONCURRENT_EVALUATIONS)
+            .collect()
+            .await
+    }
+
+    /// Log probability of `continuation` following `prompt`, i.e. the sum of the log probabilities
+    /// of its tokens. Higher is better. Shorthand for an [`Self::evaluation`] which only needs
+    /// [`EvaluateOutput::log_probability`], e.g. to build a reranker. The continuation is scored
+    /// as is, without being generated, so start it with a space if it continues a sentence.
+    ///
+    /// Longer continuations accumulate lower scores. To compare candidates of different lengths,
+    /// use [`Self::evaluation`] and [`EvaluateOutput::log_perplexity_per_token`] instead, which is
+    /// the negated mean. An empty continuation scores `0.0` without sending a request.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, Error, How, Prompt};
+    ///
+    /// async fn score() -> Result<(), Error> {
+    ///     let client = Client::from_env()?;
+    ///
+    ///     let prompt = Prompt::from_text("An apple a day");
+    ///     let score = client
+    ///         .score(&prompt, " keeps the doctor away", "luminous-base", &How::default())
+    ///         .await?;
+    ///
+    ///     println!("{score}");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn score(
+        &self,
+        prompt: &Prompt<'_>,
+        continuation: &str,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<f64, Error> {
+        if continuation.is_empty() {
+            return Ok(0.0);
+        }
+        let task = TaskEvaluate {
+            prompt: prompt.borrow(),
+            completion_expected: continuation,
+        };
+        let output = self.evaluation(&task, model, how).await?;
+        output
+            .log_probability
+            .ok_or_else(|| Error::InvalidResponse {
+                deserialization_error: "Evaluation response is missing log_probability".to_owned(),
+            })
+    }
+
     /// Tokenize a prompt for a specific model.
     ///
     /// ```no_run
@@ -388,11 +1172,11 @@ impl Client {
     pub async fn tokenize(
         &self,
         task: &TaskTokenization<'_>,
-        model: &str,
+        model: impl Into<ModelName<'_>>,
         how: &How,
     ) -> Result<TokenizationOutput, Error> {
         self.http_client
-            .output_of(&task.with_model(model), how)
+            .output_of(&task.with_model(model.into().as_str()), how)
             .await
     }
 
@@ -422,20 +1206,242 @@ impl Client {
     pub async fn detokenize(
         &self,
         task: &TaskDetokenization<'_>,
-        model: &str,
+        model: impl Into<ModelName<'_>>,
         how: &How,
     ) -> Result<DetokenizationOutput, Error> {
         self.http_client
-            .output_of(&task.with_model(model), how)
+            .output_of(&task.with_model(model.into().as_str()), how)
             .await
     }
 
+    /// Check whether the API is reachable and accepts the API token, e.g. for a readiness probe of
+    /// your service. Sends a cheap request listing the available models. A rejected token is
+    /// reported as [`Error::Unauthorized`], while an unreachable API results in
+    /// [`Error::Other`] or [`Error::ClientTimeout`]. Use [`How::client_timeout`] to fail fast.
+    pub async fn ping(&self, how: &How) -> Result<(), Error> {
+        self.models_available(how).await?;
+        Ok(())
+    }
+
+    /// List all models available to the authenticated user, together with their metadata.
+    pub async fn models_available(&self, how: &How) -> Result<Vec<ModelMetadata>, Error> {
+        self.http_client.output_of(&TaskModelsAvailable, how).await
+    }
+
+    /// Like [`Self::models_available`], but yields the models one at a time. Currently the API
+    /// returns all models in a single response, which is fetched once the stream is polled for the
+    /// first time. Should the API start paginating the listing, this stream fetches subsequent
+    /// pages transparently, so code written against it keeps working.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, How, Error};
+    /// use futures_util::StreamExt;
+    ///
+    /// async fn print_models() -> Result<(), Error> {
+    ///     let client = Client::from_env()?;
+    ///     let how = How::default();
+    ///     let mut models = Box::pin(client.models_available_stream(&how));
+    ///     while let Some(model) = models.next().await {
+    ///         println!("{}", model?.name);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn models_available_stream<'a>(
+        &'a self,
+        how: &'a How,
+    ) -> impl Stream<Item = Result<ModelMetadata, Error>> + Send + 'a {
+        try_stream! {
+            for model in self.models_available(how).await? {
+                yield model;
+            }
+        }
+    }
+
+    /// Metadata of a single model, like its context size or whether it supports images. The
+    /// metadata is reported by the API itself (see [`Self::models_available`]), so it is always up
+    /// to date with the models actually deployed. Returns [`Error::ModelNotFound`] if no model
+    /// with the given name is available.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, Error, How};
+    ///
+    /// async fn context_size() -> Result<(), Error> {
+    ///     let client = Client::from_env()?;
+    ///
+    ///     let metadata = client
+    ///         .model_metadata("pharia-1-llm-7b-control", &How::default())
+    ///         .await?;
+    ///
+    ///     println!("Context size: {}", metadata.max_context_size);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn model_metadata(
+        &self,
+        model: impl Into<ModelName<'_>>,
+        how: &How,
+    ) -> Result<ModelMetadata, Error> {
+        let model = model.into();
+        self.models_available(how)
+            .await?
+            .into_iter()
+            .find(|metadata| metadata.name == model.as_str())
+            .ok_or_else(|| Error::ModelNotFound {
+                model: model.as_str().to_owned(),
+            })
+    }
+
+    /// Send a raw JSON body to an arbitrary route of the API and return the raw JSON answer. This
+    /// allows you to use features of the API this crate does not model yet, without forking it.
+    /// Authentication, the settings in `how` and the translation of error responses (e.g. into
+    /// [`Error::TooManyRequests`] or [`Error::Busy`]) are applied just like for any other request.
+    ///
+    /// **Experimental:** This is an escape hatch and not covered by any guarantees of the typed
+    /// API. Pass `serde_json::Value::Null` as body to send a request without a body.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Client, Error, How};
+    /// use reqwest::Method;
+    /// use serde_json::json;
+    ///
+    /// async fn raw_request() -> Result<(), Error> {
+    ///     let client = Client::from_env()?;
+    ///     let body = json!({"model": "luminous-base", "prompt": "Hello", "tokens": true});
+    ///     let answer = client
+    ///         .send_raw(Method::POST, "/tokenize", &body, &How::default())
+    ///         .await?;
+    ///     println!("{answer}");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn send_raw(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: &serde_json::Value,
+        how: &How,
+    ) -> Result<serde_json::Value, Error> {
+        let job = RawJob { method, path, body };
+        self.http_client.output_of(&job, how).await
+    }
+
     pub async fn tokenizer_by_model(
         &self,
-        model: &str,
+        model: impl Into<ModelName<'_>>,
         api_token: Option<String>,
     ) -> Result<Tokenizer, Error> {
-        self.http_client.tokenizer_by_model(model, api_token).await
+        self.http_client
+            .tokenizer_by_model(model.into().as_str(), api_token)
+            .await
+    }
+
+    fn with_sampling_defaults<'a, T>(&'a self, task: &'a T) -> WithSamplingDefaults<'a, T> {
+        WithSamplingDefaults {
+            task,
+            defaults: &self.default_sampling,
+        }
+    }
+}
+
+/// Sends the items of `stream` into `sender`, waiting for capacity before pulling the next one.
+/// Stops early, without error, if the receiver has been dropped.
+#[cfg(feature = "tokio")]
+async fn forward<T>(
+    mut stream: impl Stream<Item = Result<T, Error>> + Unpin,
+    sender: tokio::sync::mpsc::Sender<T>,
+) -> Result<(), Error> {
+    while let Some(item) = stream.next().await {
+        if sender.send(item?).await.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The ability to answer completion and chat tasks. [`Client`] is the production implementation.
+/// Write your business logic against this trait, if you want to substitute a fake in your tests,
+/// without running a server. The trait is object safe, so you may also use `&dyn Completions`.
+///
+/// ```
+/// use aleph_alpha_client::{
+///     ChatOutput, Completions, CompletionOutput, Error, How, Message, TaskChat, TaskCompletion,
+///     Usage,
+/// };
+/// use futures_util::future::{self, BoxFuture};
+///
+/// /// Always answers with the same message.
+/// struct Parrot;
+///
+/// impl Completions for Parrot {
+///     fn completion<'a>(
+///         &'a self,
+///         _task: &'a TaskCompletion<'a>,
+///         _model: &'a str,
+///         _how: &'a How,
+///     ) -> BoxFuture<'a, Result<CompletionOutput, Error>> {
+///         let output = CompletionOutput {
+///             completion: " Hello".to_owned(),
+///             finish_reason: "maximum_tokens".to_owned(),
+///             model_version: "parrot".to_owned(),
+///             logprobs: Vec::new(),
+///             usage: Usage { prompt_tokens: 1, completion_tokens: 1 },
+///             num_tokens_prompt_total: Some(1),
+///             num_tokens_generated: Some(1),
+///             optimized_prompt: None,
+///             completion_tokens: None,
+///         };
+///         Box::pin(future::ready(Ok(output)))
+///     }
+///
+///     fn chat<'a>(
+///         &'a self,
+///         _task: &'a TaskChat<'a>,
+///         _model: &'a str,
+///         _how: &'a How,
+///     ) -> BoxFuture<'a, Result<ChatOutput, Error>> {
+///         let usage = Usage { prompt_tokens: 1, completion_tokens: 1 };
+///         let message = Message::assistant("Hello");
+///         let output = ChatOutput::new(message, "stop".to_owned(), Vec::new(), usage);
+///         Box::pin(future::ready(Ok(output)))
+///     }
+/// }
+/// ```
+pub trait Completions {
+    /// See [`Client::completion`].
+    fn completion<'a>(
+        &'a self,
+        task: &'a TaskCompletion<'a>,
+        model: &'a str,
+        how: &'a How,
+    ) -> BoxFuture<'a, Result<CompletionOutput, Error>>;
+
+    /// See [`Client::chat`].
+    fn chat<'a>(
+        &'a self,
+        task: &'a TaskChat<'a>,
+        model: &'a str,
+        how: &'a How,
+    ) -> BoxFuture<'a, Result<ChatOutput, Error>>;
+}
+
+impl Completions for Client {
+    fn completion<'a>(
+        &'a self,
+        task: &'a TaskCompletion<'a>,
+        model: &'a str,
+        how: &'a How,
+    ) -> BoxFuture<'a, Result<CompletionOutput, Error>> {
+        Box::pin(Client::completion(self, task, model, how))
+    }
+
+    fn chat<'a>(
+        &'a self,
+        task: &'a TaskChat<'a>,
+        model: &'a str,
+        how: &'a How,
+    ) -> BoxFuture<'a, Result<ChatOutput, Error>> {
+        Box::pin(Client::chat(self, task, model, how))
     }
 }
 
@@ -452,12 +1458,39 @@ pub struct How {
 
     /// The maximum duration of a request before the client cancels the request. This is not passed on
     /// to the server but only handled by the client locally, i.e. the client will not wait longer than
-    /// this duration for a response.
+    /// this duration for a response. Use [`ClientBuilder::with_timeout_per_token`] to extend it
+    /// for tasks which may generate many tokens.
     pub client_timeout: Duration,
 
     /// API token used to authenticate the request, overwrites the default token provided on setup
-    /// Default token may not provide the tracking or permission that is wanted for the request
+    /// Default token may not provide the tracking or permission that is wanted for the request.
+    /// A token which can not be sent in a header fails the request with [`Error::InvalidParameter`].
     pub api_token: Option<String>,
+
+    /// Send as `Idempotency-Key` header, so the server can recognize a retried request and avoid
+    /// executing (and billing) it twice. Use the same key if you send the same request again after
+    /// e.g. a timeout, and a new key for each distinct request. Omitted if `None`.
+    pub idempotency_key: Option<String>,
+
+    /// Cancel the request from elsewhere, e.g. once its result is no longer needed. The request
+    /// then fails with [`Error::Cancelled`]. `None` if the request can not be cancelled, besides
+    /// dropping its future.
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl fmt::Debug for How {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("How")
+            .field("be_nice", &self.be_nice)
+            .field("client_timeout", &self.client_timeout)
+            .field(
+                "api_token",
+                &self.api_token.as_ref().map(|_| http::REDACTED),
+            )
+            .field("idempotency_key", &self.idempotency_key)
+            .field("cancellation", &self.cancellation)
+            .finish()
+    }
 }
 
 impl Default for How {
@@ -470,6 +1503,8 @@ impl Default for How {
             // therefore by default we wait slightly longer
             client_timeout: api_timeout + Duration::from_secs(5),
             api_token: None,
+            idempotency_key: None,
+            cancellation: None,
         }
     }
 }
@@ -502,6 +1537,7 @@ impl Default for How {
 ///         prompt: robot_fact,
 ///         representation: SemanticRepresentation::Document,
 ///         compress_to_size: Some(128),
+///         normalize: false,
 ///     };
 ///     let robot_embedding = client.semantic_embedding(
 ///         &robot_embedding_task,
@@ -512,6 +1548,7 @@ impl Default for How {
 ///         prompt: pizza_fact,
 ///         representation: SemanticRepresentation::Document,
 ///         compress_to_size: Some(128),
+///         normalize: false,
 ///     };
 ///     let pizza_embedding = client.semantic_embedding(
 ///         &pizza_embedding_task,
@@ -522,6 +1559,7 @@ impl Default for How {
 ///         prompt: query,
 ///         representation: SemanticRepresentation::Query,
 ///         compress_to_size: Some(128),
+///         normalize: false,
 ///     };
 ///     let query_embedding = client.semantic_embedding(
 ///         &query_embedding_task,
@@ -549,7 +1587,7 @@ pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
 
 #[cfg(test)]
 mod tests {
-    use crate::Prompt;
+    use crate::{How, Prompt};
 
     #[test]
     fn ability_to_generate_prompt_in_local_function() {
@@ -559,4 +1597,28 @@ mod tests {
 
         assert_eq!(Prompt::from_text("My test prompt"), local_function())
     }
+
+    #[test]
+    fn debug_output_of_how_redacts_api_token() {
+        let how = How {
+            api_token: Some("secret-token".to_owned()),
+            ..How::default()
+        };
+
+        let debug = format!("{how:?}");
+
+        assert!(!debug.contains("secret-token"));
+    }
+
+    #[test]
+    fn debug_output_of_how_contains_idempotency_key() {
+        let how = How {
+            idempotency_key: Some("retry-42".to_owned()),
+            ..How::default()
+        };
+
+        let debug = format!("{how:?}");
+
+        assert!(debug.contains("retry-42"));
+    }
 }
@@ -2,7 +2,7 @@ use std::str::{self, Utf8Error};
 
 use serde::Deserialize;
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Logprobs {
     /// Do not return any logprobs
     No,
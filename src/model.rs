@@ -0,0 +1,224 @@
+use std::borrow::Cow;
+
+use serde::Deserialize;
+
+use crate::{Error, Job};
+
+/// Well known models served by the Aleph Alpha API. Use [`Model::Custom`] for any model which is
+/// not (yet) listed here.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Model {
+    LuminousBase,
+    LuminousExtended,
+    LuminousSupreme,
+    LuminousBaseControl,
+    LuminousExtendedControl,
+    LuminousSupremeControl,
+    Pharia1Llm7bControl,
+    Llama31_8bInstruct,
+    Llama31_70bInstruct,
+    /// Any model not covered by the other variants, identified by its name. E.g.
+    /// `Model::Custom("my-finetuned-model".to_owned())`.
+    Custom(String),
+}
+
+impl Model {
+    /// Name of the model, as expected by the API.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Model::LuminousBase => "luminous-base",
+            Model::LuminousExtended => "luminous-extended",
+            Model::LuminousSupreme => "luminous-supreme",
+            Model::LuminousBaseControl => "luminous-base-control",
+            Model::LuminousExtendedControl => "luminous-extended-control",
+            Model::LuminousSupremeControl => "luminous-supreme-control",
+            Model::Pharia1Llm7bControl => "pharia-1-llm-7b-control",
+            Model::Llama31_8bInstruct => "llama-3.1-8b-instruct",
+            Model::Llama31_70bInstruct => "llama-3.1-70b-instruct",
+            Model::Custom(name) => name,
+        }
+    }
+}
+
+/// The name of a model a task is executed with. The methods of [`crate::Client`] accept anything
+/// which can be converted into a model name, so you can pass either a [`Model`] or a plain string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModelName<'a>(Cow<'a, str>);
+
+impl ModelName<'_> {
+    /// Name of the model, as expected by the API.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Like [`Self::as_str`], but fails if the name is empty or whitespace only. Cheaper than
+    /// sending the request and receiving a confusing error from the server.
+    pub(crate) fn checked(&self) -> Result<&str, Error> {
+        if self.0.trim().is_empty() {
+            return Err(Error::InvalidParameter {
+                field: "model".to_owned(),
+                message: "must not be empty".to_owned(),
+            });
+        }
+        Ok(&self.0)
+    }
+}
+
+impl<'a> From<&'a str> for ModelName<'a> {
+    fn from(name: &'a str) -> Self {
+        Self(Cow::Borrowed(name))
+    }
+}
+
+impl<'a> From<&'a String> for ModelName<'a> {
+    fn from(name: &'a String) -> Self {
+        Self(Cow::Borrowed(name))
+    }
+}
+
+impl From<String> for ModelName<'_> {
+    fn from(name: String) -> Self {
+        Self(Cow::Owned(name))
+    }
+}
+
+impl<'a> From<&'a Model> for ModelName<'a> {
+    fn from(model: &'a Model) -> Self {
+        Self(Cow::Borrowed(model.as_str()))
+    }
+}
+
+impl From<Model> for ModelName<'_> {
+    fn from(model: Model) -> Self {
+        match model {
+            Model::Custom(name) => Self(Cow::Owned(name)),
+            known => Self(Cow::Owned(known.as_str().to_owned())),
+        }
+    }
+}
+
+/// Lists all models available to the authenticated user, together with their metadata. Input for
+/// [crate::Client::models_available].
+pub struct TaskModelsAvailable;
+
+/// Metadata of a model, as reported by the `/models_available` route of the API.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ModelMetadata {
+    /// Name of the model, as used to address it in tasks. E.g. `luminous-base`.
+    pub name: String,
+    /// Human readable description of the model.
+    #[serde(default)]
+    pub description: String,
+    /// Maximum number of tokens prompt and completion combined may have. Use this to truncate
+    /// prompts on the client side.
+    pub max_context_size: u32,
+    /// Maximum number of tokens the model generates for a completion, if the API limits it any
+    /// further than the context size.
+    #[serde(default)]
+    pub maximum_completion_tokens: Option<u32>,
+    /// Whether the model accepts images as part of the prompt.
+    #[serde(default)]
+    pub image_support: bool,
+    /// Kinds of embeddings the model can produce. E.g. `semantic`.
+    #[serde(default)]
+    pub embedding_types: Vec<String>,
+    /// Whether the model has been aligned to follow instructions, i.e. is suited for chat.
+    #[serde(default)]
+    pub aligned: bool,
+    /// Capabilities of the model, as reported by deployments which list them explicitly. E.g.
+    /// `multimodal`, `embedding` or `chat`. Empty if the API does not report them. The well known
+    /// ones are taken into account by predicates like [`Self::is_multimodal`], all others are only
+    /// available as raw strings here.
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+impl ModelMetadata {
+    /// `true` if the model accepts images as part of the prompt.
+    pub fn is_multimodal(&self) -> bool {
+        self.image_support || self.has_capability("multimodal")
+    }
+
+    /// `true` if the model can produce embeddings of any kind. See [`Self::embedding_types`] for
+    /// the specific kinds.
+    pub fn supports_embeddings(&self) -> bool {
+        !self.embedding_types.is_empty() || self.has_capability("embedding")
+    }
+
+    /// `true` if the model can produce semantic embeddings, e.g. with
+    /// [`crate::Client::semantic_embedding`].
+    pub fn supports_semantic_embeddings(&self) -> bool {
+        self.embedding_types.iter().any(|kind| kind == "semantic")
+    }
+
+    /// `true` if the model is suited for chat, e.g. with [`crate::Client::chat`].
+    pub fn supports_chat(&self) -> bool {
+        self.aligned || self.has_capability("chat")
+    }
+
+    /// `true` if `capability` is listed in [`Self::capabilities`].
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|listed| listed == capability)
+    }
+}
+
+impl Job for TaskModelsAvailable {
+    type Output = Vec<ModelMetadata>;
+    type ResponseBody = Vec<ModelMetadata>;
+
+    fn build_request(&self, client: &reqwest::Client, base: &str) -> reqwest::RequestBuilder {
+        client.get(format!("{base}/models_available"))
+    }
+
+    fn body_to_output(&self, response: Self::ResponseBody) -> Self::Output {
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_model_keeps_its_name() {
+        let name: ModelName = Model::Custom("my-model".to_owned()).into();
+
+        assert_eq!("my-model", name.as_str());
+    }
+
+    #[test]
+    fn blank_model_name_is_rejected() {
+        let name: ModelName = " ".into();
+
+        let error = name.checked().unwrap_err();
+
+        assert!(matches!(error, Error::InvalidParameter { field, .. } if field == "model"));
+    }
+
+    #[test]
+    fn model_and_str_result_in_same_name() {
+        let from_model: ModelName = Model::LuminousBase.into();
+        let from_str: ModelName = "luminous-base".into();
+
+        assert_eq!(from_model, from_str);
+    }
+
+    #[test]
+    fn capabilities_are_derived_from_flags_and_capability_list() {
+        let metadata: ModelMetadata = serde_json::from_str(
+            r#"{
+                "name": "my-model",
+                "max_context_size": 8192,
+                "embedding_types": ["semantic"],
+                "capabilities": ["multimodal", "function_calling"]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(metadata.is_multimodal());
+        assert!(metadata.supports_embeddings());
+        assert!(metadata.supports_semantic_embeddings());
+        assert!(!metadata.supports_chat());
+        assert!(metadata.has_capability("function_calling"));
+    }
+}
@@ -0,0 +1,18 @@
+use serde::Serialize;
+
+/// How urgently a request should be processed, for deployments which schedule requests by
+/// priority or offer several hosting tiers. Sent as the `priority` parameter of completion and
+/// chat requests, e.g. `"priority": "batch"`, and omitted if unset.
+///
+/// The hosted Aleph Alpha API does not support this parameter. It is intended for on-premise
+/// deployments and gateways which do, e.g. to run offline jobs on a cheaper tier, while
+/// interactive requests are served first. Other deployments may ignore or reject it.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// A user is waiting for the answer. Served before batch requests.
+    Interactive,
+    /// Nobody is waiting for the answer right away, e.g. an offline evaluation. May be served with
+    /// a higher latency, but at a lower cost.
+    Batch,
+}
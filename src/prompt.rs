@@ -1,26 +1,64 @@
 use std::{
     borrow::{Borrow, Cow},
+    fmt,
     path::Path,
 };
 
 use base64::{prelude::BASE64_STANDARD, Engine};
 use image::DynamicImage;
 use itertools::Itertools;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::image_preprocessing::{self, LoadImageError};
 
 /// A prompt which is passed to the model for inference. Usually it is one text item, but it could
 /// also be a combination of several modalities like images and text.
-#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+///
+/// Both [`fmt::Display`] and [`fmt::Debug`] show images only by their size, e.g. `<image: 1024
+/// bytes>`, rather than dumping their base64 encoding, so prompts can be logged.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Prompt<'a>(Vec<Modality<'a>>);
 
+/// The text of the prompt, with images in between shown as `<image: N bytes>`.
+impl fmt::Display for Prompt<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|item| write!(f, "{item}"))
+    }
+}
+
 impl<'a> Prompt<'a> {
     /// Create a prompt from a single text item.
     pub fn from_text(text: impl Into<Cow<'a, str>>) -> Self {
         Self(vec![Modality::from_text(text)])
     }
 
+    /// Create a text prompt from the contents of `reader`, e.g. a file or a network stream. The
+    /// text is read in chunks until the end of the source, so you do not need to hold the raw bytes
+    /// and the string at the same time. Fails with [`crate::Error::ReadPrompt`] if reading fails or
+    /// the text is not valid UTF-8.
+    ///
+    /// ```no_run
+    /// use aleph_alpha_client::{Error, Prompt};
+    ///
+    /// async fn prompt_from_file() -> Result<Prompt<'static>, Error> {
+    ///     let file = tokio::fs::File::open("document.txt").await.map_err(Error::ReadPrompt)?;
+    ///     Prompt::from_reader(file).await
+    /// }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub async fn from_reader(
+        reader: impl tokio::io::AsyncRead,
+    ) -> Result<Prompt<'static>, crate::Error> {
+        use tokio::io::AsyncReadExt;
+
+        let mut text = String::new();
+        std::pin::pin!(reader)
+            .read_to_string(&mut text)
+            .await
+            .map_err(crate::Error::ReadPrompt)?;
+        Ok(Prompt::from_text(text))
+    }
+
     /// Create a multimodal prompt from a list of individual items with any modality.
     pub fn from_vec(items: Vec<Modality<'a>>) -> Self {
         Self(items)
@@ -31,6 +69,27 @@ impl<'a> Prompt<'a> {
         Self(self.0.iter().map(|item| item.borrow()).collect())
     }
 
+    /// Number of characters in the text items of the prompt. Images are not counted. Cheap to
+    /// compute, so you can reject obviously too long prompts before tokenizing them.
+    pub fn char_len(&self) -> usize {
+        self.0
+            .iter()
+            .map(|item| match item {
+                Modality::Text { data } => data.chars().count(),
+                Modality::Image { .. } => 0,
+            })
+            .sum()
+    }
+
+    /// Rough **estimate** of the number of tokens in the text items of the prompt, assuming four
+    /// characters per token, which holds approximately for English text. The actual number depends
+    /// on the tokenizer of the model and can differ considerably, e.g. for code or other languages.
+    /// Images are not counted. Use this as a pre-filter only and [`crate::Client::tokenize`] if you
+    /// need the exact number.
+    pub fn estimated_tokens(&self) -> usize {
+        self.char_len().div_ceil(4)
+    }
+
     /// When constructing prompts programatically, it can be beneficial to append several
     /// text items in a prompt. For example, if doing a fewshot prompt as the first item,
     /// and user input as a second item.
@@ -58,9 +117,101 @@ impl<'a> Prompt<'a> {
     }
 }
 
+/// Builds a few-shot prompt from an instruction, a couple of examples and a final query. All
+/// parts are trimmed and joined with consistent separators, so the rendered prompt does not depend
+/// on stray whitespace in the inputs.
+///
+/// ```
+/// use aleph_alpha_client::FewShot;
+///
+/// let prompt = FewShot::new("Translate English to German.")
+///     .example("Hello", "Hallo")
+///     .example("Thank you", "Danke")
+///     .query("Good morning");
+/// ```
+///
+/// renders into:
+///
+/// ```text
+/// Translate English to German.
+///
+/// Input: Hello
+/// Output: Hallo
+///
+/// Input: Thank you
+/// Output: Danke
+///
+/// Input: Good morning
+/// Output:
+/// ```
+///
+/// The prompt ends with the output label (without trailing whitespace) as a cue for the model to
+/// complete the answer.
+#[derive(Debug, Clone)]
+pub struct FewShot<'a> {
+    instruction: Cow<'a, str>,
+    examples: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    input_label: Cow<'a, str>,
+    output_label: Cow<'a, str>,
+}
+
+impl<'a> FewShot<'a> {
+    /// Start a few-shot prompt with an instruction describing the task. Use an empty string if you
+    /// want to rely on the examples alone.
+    pub fn new(instruction: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            instruction: instruction.into(),
+            examples: Vec::new(),
+            input_label: Cow::Borrowed("Input:"),
+            output_label: Cow::Borrowed("Output:"),
+        }
+    }
+
+    /// Replace the default labels `Input:` and `Output:` preceding inputs and outputs.
+    pub fn with_labels(
+        mut self,
+        input_label: impl Into<Cow<'a, str>>,
+        output_label: impl Into<Cow<'a, str>>,
+    ) -> Self {
+        self.input_label = input_label.into();
+        self.output_label = output_label.into();
+        self
+    }
+
+    /// Append an example demonstrating the expected output for an input.
+    pub fn example(
+        mut self,
+        input: impl Into<Cow<'a, str>>,
+        output: impl Into<Cow<'a, str>>,
+    ) -> Self {
+        self.examples.push((input.into(), output.into()));
+        self
+    }
+
+    /// Render the prompt, ending with the input the model should produce an output for.
+    pub fn query(&self, input: &str) -> Prompt<'static> {
+        let input_label = self.input_label.trim();
+        let output_label = self.output_label.trim();
+        let mut blocks = Vec::with_capacity(self.examples.len() + 2);
+        let instruction = self.instruction.trim();
+        if !instruction.is_empty() {
+            blocks.push(instruction.to_owned());
+        }
+        for (example_input, example_output) in &self.examples {
+            blocks.push(format!(
+                "{input_label} {}\n{output_label} {}",
+                example_input.trim(),
+                example_output.trim()
+            ));
+        }
+        blocks.push(format!("{input_label} {}\n{output_label}", input.trim()));
+        Prompt::from_text(blocks.join("\n\n"))
+    }
+}
+
 /// The prompt for models can be a combination of different modalities (Text and Image). The type of
 /// modalities which are supported depend on the Model in question.
-#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Modality<'a> {
     /// The only type of prompt which can be used with pure language models
@@ -99,6 +250,11 @@ impl<'a> Modality<'a> {
     ///         sampling: Sampling::MOST_LIKELY,
     ///         special_tokens: false,
     ///         logprobs: Logprobs::No,
+    ///         disable_optimizations: None,
+    ///         guided_json: None,
+    ///         tokens: false,
+    ///         extra: serde_json::Map::new(),
+    ///         priority: None,
     ///     };
     ///     // Execute
     ///     let model = "luminous-base";
@@ -153,6 +309,40 @@ impl<'a> Modality<'a> {
     }
 }
 
+/// Text is shown as is, images as `<image: N bytes>`.
+impl fmt::Display for Modality<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Modality::Text { data } => f.write_str(data),
+            Modality::Image { data } => write!(f, "<image: {} bytes>", decoded_len(data)),
+        }
+    }
+}
+
+/// Like the derived implementation, but shows images by their size, instead of their base64
+/// encoding, which may be megabytes long.
+impl fmt::Debug for Modality<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Modality::Text { data } => f.debug_struct("Text").field("data", data).finish(),
+            Modality::Image { data } => f
+                .debug_struct("Image")
+                .field("bytes", &decoded_len(data))
+                .finish(),
+        }
+    }
+}
+
+/// Number of bytes encoded by the base64 string, computed without decoding it.
+fn decoded_len(base64: &str) -> usize {
+    let padding = base64
+        .bytes()
+        .rev()
+        .take_while(|&byte| byte == b'=')
+        .count();
+    (base64.len() / 4 * 3).saturating_sub(padding)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -174,4 +364,80 @@ mod tests {
 
         assert_eq!(prompt.0, vec![Modality::from_text("foo\nbar")]);
     }
+
+    #[test]
+    fn length_only_counts_text() {
+        let prompt = Prompt::from_vec(vec![
+            Modality::from_text("Grüße"),
+            Modality::Image {
+                data: "aGVsbG8=".into(),
+            },
+            Modality::from_text(" aus Heidelberg"),
+        ]);
+
+        assert_eq!(prompt.char_len(), 20);
+        assert_eq!(prompt.estimated_tokens(), 5);
+    }
+
+    #[test]
+    fn images_are_logged_by_their_size() {
+        let prompt = Prompt::from_vec(vec![
+            Modality::Image {
+                data: "aGVsbG8=".into(),
+            },
+            Modality::from_text("A picture of"),
+        ]);
+
+        assert_eq!(prompt.to_string(), "<image: 5 bytes>A picture of");
+        assert_eq!(
+            format!("{prompt:?}"),
+            r#"Prompt([Image { bytes: 5 }, Text { data: "A picture of" }])"#
+        );
+    }
+
+    #[test]
+    fn few_shot_prompt_is_rendered_with_consistent_separators() {
+        let prompt = FewShot::new("Translate English to German. ")
+            .example(" Hello", "Hallo\n")
+            .example("Thank you", " Danke")
+            .query("Good morning ");
+
+        assert_eq!(
+            prompt,
+            Prompt::from_text(
+                "Translate English to German.\n\nInput: Hello\nOutput: Hallo\n\n\
+                Input: Thank you\nOutput: Danke\n\nInput: Good morning\nOutput:"
+            )
+        );
+    }
+
+    #[test]
+    fn few_shot_prompt_without_instruction() {
+        let prompt = FewShot::new("")
+            .with_labels("Q:", "A:")
+            .example("1 + 1", "2")
+            .query("2 + 2");
+
+        assert_eq!(prompt, Prompt::from_text("Q: 1 + 1\nA: 2\n\nQ: 2 + 2\nA:"));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn prompt_from_reader_contains_text() {
+        let reader: &[u8] = "An apple a day".as_bytes();
+
+        let prompt = Prompt::from_reader(reader).await.unwrap();
+
+        assert_eq!(prompt, Prompt::from_text("An apple a day"));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn invalid_utf8_from_reader_is_reported() {
+        let reader: &[u8] = &[0xff, 0xfe];
+
+        let error = Prompt::from_reader(reader).await.unwrap_err();
+
+        assert!(matches!(error, crate::Error::ReadPrompt(_)));
+    }
 }
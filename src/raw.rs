@@ -0,0 +1,33 @@
+use reqwest::Method;
+use serde_json::Value;
+
+use crate::Job;
+
+/// Arbitrary JSON request to a route of the API, which is not modelled by this crate (yet). See
+/// [`crate::Client::send_raw`].
+pub(crate) struct RawJob<'a> {
+    pub method: Method,
+    /// Path of the route relative to the host, e.g. `/some_new_route`.
+    pub path: &'a str,
+    /// JSON body of the request. `Value::Null` sends no body at all.
+    pub body: &'a Value,
+}
+
+impl Job for RawJob<'_> {
+    type Output = Value;
+    type ResponseBody = Value;
+
+    fn build_request(&self, client: &reqwest::Client, base: &str) -> reqwest::RequestBuilder {
+        let path = self.path.trim_start_matches('/');
+        let builder = client.request(self.method.clone(), format!("{base}/{path}"));
+        if self.body.is_null() {
+            builder
+        } else {
+            builder.json(self.body)
+        }
+    }
+
+    fn body_to_output(&self, response: Self::ResponseBody) -> Self::Output {
+        response
+    }
+}
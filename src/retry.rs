@@ -0,0 +1,163 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use crate::Error;
+
+/// Controls whether and how long requests are retried, which failed due to a transient condition,
+/// i.e. [`Error::TooManyRequests`], [`Error::Busy`], [`Error::Unavailable`] or a failure to
+/// connect. Set it with [`crate::ClientBuilder::with_retry_policy`]. By default requests are not
+/// retried.
+///
+/// The delay between two attempts grows exponentially, starting at `initial_backoff` and capped at
+/// `max_backoff`. Each delay is randomized between half and all of its nominal value (jitter), so
+/// many clients failing at the same time do not retry in lockstep.
+///
+/// ```
+/// use std::time::Duration;
+/// use aleph_alpha_client::RetryPolicy;
+///
+/// // Retry up to five times, but give up after 30 seconds in total.
+/// let policy = RetryPolicy::new(5).with_max_elapsed(Duration::from_secs(30));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of retries after the first attempt. `0` disables retries.
+    pub max_retries: u32,
+    /// Nominal delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper limit for the nominal delay between two attempts.
+    pub max_backoff: Duration,
+    /// Wall clock budget for all attempts of a request, measured from the start of the first
+    /// attempt. No retry is started, if waiting for it would exceed the budget, even if there are
+    /// retries left. In this case the error of the last attempt is returned. `None` means no limit
+    /// besides `max_retries`.
+    pub max_elapsed: Option<Duration>,
+}
+
+impl RetryPolicy {
+    /// Never retry a request.
+    pub const NONE: Self = RetryPolicy {
+        max_retries: 0,
+        initial_backoff: Duration::from_millis(500),
+        max_backoff: Duration::from_secs(30),
+        max_elapsed: None,
+    };
+
+    /// Retry up to `max_retries` times, starting with a delay of half a second, which doubles with
+    /// every attempt up to 30 seconds.
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Self::NONE
+        }
+    }
+
+    /// Stop retrying once the time spent on a request would exceed `max_elapsed`. See
+    /// [`Self::max_elapsed`].
+    pub fn with_max_elapsed(mut self, max_elapsed: Duration) -> Self {
+        self.max_elapsed = Some(max_elapsed);
+        self
+    }
+
+    /// Change the delays between attempts. See [`Self::initial_backoff`] and
+    /// [`Self::max_backoff`].
+    pub fn with_backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Delay before retry number `retry` (starting at `0`), or `None` if the request should not be
+    /// retried (again). `elapsed` is the time spent on the request so far.
+    pub(crate) fn backoff(&self, retry: u32, elapsed: Duration, error: &Error) -> Option<Duration> {
+        if retry >= self.max_retries || !is_transient(error) {
+            return None;
+        }
+        let nominal = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+        let delay = nominal.mul_f64(0.5 + 0.5 * random_fraction());
+        match self.max_elapsed {
+            Some(max_elapsed) if elapsed + delay > max_elapsed => None,
+            _ => Some(delay),
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// `true` for errors which are likely to go away, if the same request is sent again later.
+fn is_transient(error: &Error) -> bool {
+    match error {
+        Error::TooManyRequests { .. } | Error::Busy { .. } | Error::Unavailable { .. } => true,
+        Error::Other(reqwest_error) => reqwest_error.is_connect(),
+        _ => false,
+    }
+}
+
+/// Uniformly distributed in `[0, 1]`. Good enough for jitter, without depending on a random number
+/// generator. `RandomState` is seeded differently every time it is created.
+fn random_fraction() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    random as f64 / u64::MAX as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unavailable() -> Error {
+        Error::Unavailable {
+            body: String::new(),
+        }
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_maximum() {
+        let policy =
+            RetryPolicy::new(10).with_backoff(Duration::from_secs(1), Duration::from_secs(4));
+
+        let delays: Vec<_> = (0..4)
+            .map(|retry| {
+                policy
+                    .backoff(retry, Duration::ZERO, &unavailable())
+                    .unwrap()
+            })
+            .collect();
+
+        let nominal = [1, 2, 4, 4].map(Duration::from_secs);
+        for (delay, nominal) in delays.into_iter().zip(nominal) {
+            assert!(delay >= nominal / 2 && delay <= nominal);
+        }
+    }
+
+    #[test]
+    fn no_retry_beyond_time_budget() {
+        let policy = RetryPolicy::new(10)
+            .with_backoff(Duration::from_secs(2), Duration::from_secs(2))
+            .with_max_elapsed(Duration::from_secs(30));
+
+        let backoff = policy.backoff(3, Duration::from_secs(29), &unavailable());
+
+        assert_eq!(backoff, None);
+    }
+
+    #[test]
+    fn invalid_parameters_are_not_retried() {
+        let policy = RetryPolicy::new(10);
+        let error = Error::InvalidParameter {
+            field: "temperature".to_owned(),
+            message: "must be positive".to_owned(),
+        };
+
+        assert_eq!(policy.backoff(0, Duration::ZERO, &error), None);
+    }
+}
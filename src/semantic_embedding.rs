@@ -1,10 +1,15 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
+use tokenizers::Tokenizer;
 
 use crate::{http::Task, Job, Prompt};
 
+/// Number of prompts [`crate::Client::embed_all`] sends in a single batch. This is the maximum
+/// number of prompts the API accepts in one request to `/batch_semantic_embed`.
+pub const DEFAULT_EMBEDDING_BATCH_SIZE: usize = 100;
+
 /// Allows you to choose a semantic representation fitting for your usecase.
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum SemanticRepresentation {
     /// Useful for comparing prompts to each other, in use cases such as clustering, classification,
@@ -31,7 +36,9 @@ pub struct TaskSemanticEmbedding<'a> {
     pub representation: SemanticRepresentation,
     /// Default behaviour is to return the full embedding, but you can optionally request an
     /// embedding compressed to a smaller set of dimensions. A size of `128` is supported for every
-    /// model.
+    /// model, and currently the only size the API accepts. The API rejects other values, which this
+    /// client does not check beforehand. The returned embeddings have exactly as many dimensions
+    /// as requested.
     ///
     /// The 128 size is expected to have a small drop in accuracy performance (4-6%), with the
     /// benefit of being much smaller, which makes comparing these embeddings much faster for use
@@ -40,6 +47,12 @@ pub struct TaskSemanticEmbedding<'a> {
     /// The 128 size can also perform better if you are embedding short texts or documents.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub compress_to_size: Option<u32>,
+    /// Return embeddings scaled to unit length (L2 norm of 1). Cosine similarity of normalized
+    /// embeddings equals their dot product, which is cheaper to compute and what many vector
+    /// stores expect. The direction and thus the cosine similarity is not changed, but the
+    /// magnitude is lost. Defaults to `false`, like the API.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub normalize: bool,
 }
 
 /// Appends model and hosting to the bare task
@@ -109,7 +122,9 @@ pub struct TaskBatchSemanticEmbedding<'a> {
     pub representation: SemanticRepresentation,
     /// Default behaviour is to return the full embedding, but you can optionally request an
     /// embedding compressed to a smaller set of dimensions. A size of `128` is supported for every
-    /// model.
+    /// model, and currently the only size the API accepts. The API rejects other values, which this
+    /// client does not check beforehand. The returned embeddings have exactly as many dimensions
+    /// as requested.
     ///
     /// The 128 size is expected to have a small drop in accuracy performance (4-6%), with the
     /// benefit of being much smaller, which makes comparing these embeddings much faster for use
@@ -118,6 +133,12 @@ pub struct TaskBatchSemanticEmbedding<'a> {
     /// The 128 size can also perform better if you are embedding short texts or documents.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub compress_to_size: Option<u32>,
+    /// Return embeddings scaled to unit length (L2 norm of 1). Cosine similarity of normalized
+    /// embeddings equals their dot product, which is cheaper to compute and what many vector
+    /// stores expect. The direction and thus the cosine similarity is not changed, but the
+    /// magnitude is lost. Defaults to `false`, like the API.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub normalize: bool,
 }
 
 /// Heap allocated vec of embeddings. Can hold full embeddings or compressed ones
@@ -126,6 +147,30 @@ pub struct BatchSemanticEmbeddingOutput {
     pub embeddings: Vec<Vec<f32>>,
 }
 
+impl Task for TaskBatchSemanticEmbedding<'_> {
+    type Output = BatchSemanticEmbeddingOutput;
+    type ResponseBody = BatchSemanticEmbeddingOutput;
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        base: &str,
+        model: &str,
+    ) -> reqwest::RequestBuilder {
+        let body = RequestBody {
+            model,
+            semantic_embedding_task: self,
+        };
+        client
+            .post(format!("{base}/batch_semantic_embed"))
+            .json(&body)
+    }
+
+    fn body_to_output(&self, response: Self::ResponseBody) -> Self::Output {
+        response
+    }
+}
+
 impl Job for TaskBatchSemanticEmbedding<'_> {
     type Output = BatchSemanticEmbeddingOutput;
     type ResponseBody = BatchSemanticEmbeddingOutput;
@@ -145,3 +190,97 @@ impl Job for TaskBatchSemanticEmbedding<'_> {
         response
     }
 }
+
+/// Splits `text` into chunks of at most `max_tokens` tokens, e.g. to embed a long document piece
+/// by piece. Consecutive chunks share `overlap` tokens, so context at the boundaries is not lost.
+/// Chunks are cut at token boundaries, which never split a character, and whitespace between two
+/// tokens at a boundary is dropped. Use the tokenizer of the embedding model, see
+/// [`crate::Client::tokenizer_by_model`].
+///
+/// # Panics
+///
+/// If `overlap` is not smaller than `max_tokens`.
+pub fn chunk_by_tokens(
+    text: &str,
+    max_tokens: usize,
+    overlap: usize,
+    tokenizer: &Tokenizer,
+) -> Result<Vec<String>, tokenizers::Error> {
+    assert!(
+        overlap < max_tokens,
+        "overlap must be smaller than max_tokens to make progress"
+    );
+    let encoding = tokenizer.encode(text, false)?;
+    let offsets = encoding.get_offsets();
+    let step = max_tokens - overlap;
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < offsets.len() {
+        let end = (start + max_tokens).min(offsets.len());
+        chunks.push(text[offsets[start].0..offsets[end - 1].1].to_owned());
+        if end == offsets.len() {
+            break;
+        }
+        start += step;
+    }
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokenizers::{models::wordlevel::WordLevel, pre_tokenizers::whitespace::Whitespace};
+
+    use super::*;
+
+    /// Tokenizer with one token per word, so token boundaries are easy to reason about.
+    fn word_tokenizer() -> Tokenizer {
+        let vocab = ["[UNK]", "one", "two", "three", "four", "five", "grüße"]
+            .into_iter()
+            .enumerate()
+            .map(|(id, word)| (word.to_owned(), id as u32))
+            .collect();
+        let model = WordLevel::builder()
+            .vocab(vocab)
+            .unk_token("[UNK]".to_owned())
+            .build()
+            .unwrap();
+        let mut tokenizer = Tokenizer::new(model);
+        tokenizer.with_pre_tokenizer(Some(Whitespace {}));
+        tokenizer
+    }
+
+    #[test]
+    fn text_fitting_exactly_is_one_chunk() {
+        let chunks = chunk_by_tokens("one two three", 3, 0, &word_tokenizer()).unwrap();
+
+        assert_eq!(chunks, vec!["one two three"]);
+    }
+
+    #[test]
+    fn chunks_without_overlap() {
+        let chunks = chunk_by_tokens("one two three four five", 2, 0, &word_tokenizer()).unwrap();
+
+        assert_eq!(chunks, vec!["one two", "three four", "five"]);
+    }
+
+    #[test]
+    fn consecutive_chunks_overlap() {
+        let chunks = chunk_by_tokens("one two three four five", 3, 1, &word_tokenizer()).unwrap();
+
+        assert_eq!(chunks, vec!["one two three", "three four five"]);
+    }
+
+    #[test]
+    fn multibyte_characters_are_kept_intact() {
+        let chunks = chunk_by_tokens("grüße grüße grüße", 2, 0, &word_tokenizer()).unwrap();
+
+        assert_eq!(chunks, vec!["grüße grüße", "grüße"]);
+    }
+
+    #[test]
+    fn empty_text_has_no_chunks() {
+        let chunks = chunk_by_tokens("", 2, 0, &word_tokenizer()).unwrap();
+
+        assert!(chunks.is_empty());
+    }
+}
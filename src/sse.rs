@@ -0,0 +1,136 @@
+use std::mem;
+
+/// Extracts the data of server sent events from a stream of bytes, which may be split at arbitrary
+/// positions, e.g. in the middle of a line or even a UTF-8 character.
+///
+/// Only the `data` field of events is of interest to us. Comments (lines starting with `:`), e.g.
+/// sent by proxies as keep-alive pings, and other fields like `event` or `id` are ignored. Events
+/// without data are skipped. The stream terminates with an event whose data is `[DONE]`, which is
+/// not reported itself.
+#[derive(Default)]
+pub(crate) struct SseParser {
+    /// Bytes received, which do not form a complete line yet.
+    buffer: Vec<u8>,
+    /// Data of the event currently received, if any `data` line has been seen for it yet.
+    data: Option<String>,
+    /// `true` once `[DONE]` has been received.
+    done: bool,
+}
+
+impl SseParser {
+    /// Feeds the next bytes of the stream into the parser. Returns the data of all events which
+    /// have been completed by these bytes.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        while !self.done {
+            let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') else {
+                break;
+            };
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            self.process_line(&String::from_utf8_lossy(&line), &mut events);
+        }
+        events
+    }
+
+    /// Call once the stream has ended. Returns the data of the last event, in case the stream
+    /// has not been terminated with an empty line.
+    pub fn finish(mut self) -> Vec<String> {
+        let mut events = Vec::new();
+        if !self.done {
+            let rest = mem::take(&mut self.buffer);
+            self.process_line(&String::from_utf8_lossy(&rest), &mut events);
+            self.process_line("", &mut events);
+        }
+        events
+    }
+
+    /// `true` if the server signaled the end of the stream. No more events are reported.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    fn process_line(&mut self, line: &str, events: &mut Vec<String>) {
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line.is_empty() {
+            // An empty line dispatches the event
+            if let Some(data) = self.data.take() {
+                if data.trim() == "[DONE]" {
+                    self.done = true;
+                } else if !data.trim().is_empty() {
+                    events.push(data);
+                }
+            }
+        } else if let Some(value) = line.strip_prefix("data:") {
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match &mut self.data {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => self.data = Some(value.to_owned()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_split_across_reads_are_assembled() {
+        let mut parser = SseParser::default();
+
+        let first = parser.push(b"data: {\"a\":");
+        let second = parser.push(b" 1}\n");
+        let third = parser.push(b"\ndata: {\"b\": 2}\n\n");
+
+        assert!(first.is_empty());
+        assert!(second.is_empty());
+        assert_eq!(third, ["{\"a\": 1}", "{\"b\": 2}"]);
+    }
+
+    #[test]
+    fn multibyte_character_split_across_reads() {
+        let mut parser = SseParser::default();
+        let bytes = "data: \"grüße\"\n\n".as_bytes();
+        // Split in the middle of 'ü'
+        let split = 10;
+
+        let mut events = parser.push(&bytes[..split]);
+        events.extend(parser.push(&bytes[split..]));
+
+        assert_eq!(events, ["\"grüße\""]);
+    }
+
+    #[test]
+    fn comments_and_keep_alives_are_ignored() {
+        let mut parser = SseParser::default();
+
+        let events = parser.push(b": keep-alive\n\n\r\nevent: message\r\ndata: 42\r\n\r\n");
+
+        assert_eq!(events, ["42"]);
+    }
+
+    #[test]
+    fn done_terminates_stream() {
+        let mut parser = SseParser::default();
+
+        let events = parser.push(b"data: 1\n\ndata: [DONE]\n\ndata: 2\n\n");
+
+        assert_eq!(events, ["1"]);
+        assert!(parser.is_done());
+        assert!(parser.finish().is_empty());
+    }
+
+    #[test]
+    fn last_event_without_empty_line_is_reported_on_finish() {
+        let mut parser = SseParser::default();
+
+        let events = parser.push(b"data: 1");
+
+        assert!(events.is_empty());
+        assert_eq!(parser.finish(), ["1"]);
+    }
+}
@@ -1,3 +1,10 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures_util::Stream;
 use reqwest::RequestBuilder;
 use serde::Deserialize;
 
@@ -23,6 +30,12 @@ pub trait StreamJob {
 
     /// Parses the response of the server into higher level structs for the user.
     fn body_to_output(response: Self::ResponseBody) -> Self::Output;
+
+    /// Upper bound of tokens the model generates for this job, if any. Scales the timeout, see
+    /// [`crate::ClientBuilder::with_timeout_per_token`].
+    fn maximum_tokens(&self) -> Option<u32> {
+        None
+    }
 }
 
 /// A task send to the Aleph Alpha Api using the http client. Requires to specify a model before it
@@ -41,6 +54,12 @@ pub trait StreamTask {
     /// Parses the response of the server into higher level structs for the user.
     fn body_to_output(response: Self::ResponseBody) -> Self::Output;
 
+    /// Upper bound of tokens the model generates for this job, if any. Scales the timeout, see
+    /// [`crate::ClientBuilder::with_timeout_per_token`].
+    fn maximum_tokens(&self) -> Option<u32> {
+        None
+    }
+
     /// Turn your task into [`Job`] by annotating it with a model name.
     fn with_model<'a>(&'a self, model: &'a str) -> MethodJob<'a, Self>
     where
@@ -65,4 +84,127 @@ where
     fn body_to_output(response: T::ResponseBody) -> T::Output {
         T::body_to_output(response)
     }
+
+    fn maximum_tokens(&self) -> Option<u32> {
+        self.task.maximum_tokens()
+    }
+}
+
+/// Latency of a stream, as measured by [`MeasuredStream`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamMetrics {
+    /// Time from creating the [`MeasuredStream`] until the first item arrived. `None` if the
+    /// stream has not yielded any item yet.
+    pub time_to_first_item: Option<Duration>,
+    /// Number of items yielded so far. For a chat stream every item corresponds roughly to one
+    /// token, but the stream also contains items without content, like the final usage.
+    pub num_items: usize,
+    /// Time from creating the [`MeasuredStream`] until the last item arrived. Zero if no item
+    /// arrived yet.
+    pub total_duration: Duration,
+}
+
+impl StreamMetrics {
+    /// Average time between two consecutive items, after the first one arrived. `None` if less
+    /// than two items have been yielded.
+    pub fn mean_inter_item_latency(&self) -> Option<Duration> {
+        let first = self.time_to_first_item?;
+        let gaps = u32::try_from(self.num_items.checked_sub(1)?).ok()?;
+        (gaps > 0).then(|| (self.total_duration - first) / gaps)
+    }
+}
+
+/// Wraps a stream, e.g. returned by [`crate::Client::stream_chat`], and measures when its items
+/// arrive. The clock starts once the `MeasuredStream` is created, so create it right after the
+/// stream has been returned to measure the latency of the model, rather than of your own code.
+/// Measuring merely reads the clock once per item.
+///
+/// ```no_run
+/// use aleph_alpha_client::{Client, How, MeasuredStream, Message, TaskChat, Error};
+/// use futures_util::StreamExt;
+///
+/// async fn time_to_first_token() -> Result<(), Error> {
+///     let client = Client::from_env()?;
+///     let task = TaskChat::with_message(Message::user("Hello"));
+///     let stream = client.stream_chat(&task, "pharia-1-llm-7b-control", &How::default()).await?;
+///     let mut stream = MeasuredStream::new(stream);
+///     while let Some(event) = stream.next().await {
+///         event?;
+///     }
+///     println!("{:?}", stream.metrics().time_to_first_item);
+///     Ok(())
+/// }
+/// ```
+pub struct MeasuredStream<S> {
+    inner: S,
+    started: Instant,
+    metrics: StreamMetrics,
+}
+
+impl<S> MeasuredStream<S> {
+    /// Starts the clock and wraps `inner`.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            started: Instant::now(),
+            metrics: StreamMetrics {
+                time_to_first_item: None,
+                num_items: 0,
+                total_duration: Duration::ZERO,
+            },
+        }
+    }
+
+    /// Metrics of the items yielded so far. Final once the stream is consumed.
+    pub fn metrics(&self) -> StreamMetrics {
+        self.metrics
+    }
+}
+
+impl<S> Stream for MeasuredStream<S>
+where
+    S: Stream + Unpin,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.inner).poll_next(cx);
+        if let Poll::Ready(Some(_)) = &poll {
+            let elapsed = self.started.elapsed();
+            let metrics = &mut self.metrics;
+            metrics.time_to_first_item.get_or_insert(elapsed);
+            metrics.num_items += 1;
+            metrics.total_duration = elapsed;
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{stream, StreamExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn items_are_counted() {
+        let mut measured = MeasuredStream::new(stream::iter([1, 2, 3]));
+
+        while measured.next().await.is_some() {}
+
+        let metrics = measured.metrics();
+        assert_eq!(metrics.num_items, 3);
+        assert!(metrics.time_to_first_item.unwrap() <= metrics.total_duration);
+        assert!(metrics.mean_inter_item_latency().is_some());
+    }
+
+    #[test]
+    fn no_latencies_before_first_item() {
+        let measured = MeasuredStream::new(stream::iter([1]));
+
+        let metrics = measured.metrics();
+
+        assert_eq!(metrics.time_to_first_item, None);
+        assert_eq!(metrics.mean_inter_item_latency(), None);
+    }
 }
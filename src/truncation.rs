@@ -0,0 +1,41 @@
+use crate::{Message, Role};
+
+/// What [`crate::TaskChat::truncate_to`] does with a conversation exceeding the token limit.
+/// System messages and the latest message are never dropped, since without them the model would
+/// answer a different question. Defaults to [`TruncationStrategy::Error`], so no part of a
+/// conversation is lost without you noticing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TruncationStrategy {
+    /// Leave the conversation unchanged and let the server decide, e.g. by rejecting it with
+    /// [`crate::Error::PromptTooLong`].
+    None,
+    /// Drop the oldest messages first, e.g. for chat bots where recent turns matter most.
+    DropOldest,
+    /// Keep the first message after the system messages, which often states the task, and drop
+    /// messages from the middle of the conversation.
+    DropMiddle,
+    /// Fail with [`crate::Error::PromptTooLong`] without dropping anything.
+    #[default]
+    Error,
+}
+
+impl TruncationStrategy {
+    /// Index of the message to drop next, or `None` if none of the messages may be dropped.
+    pub(crate) fn next_to_drop(self, messages: &[Message<'_>]) -> Option<usize> {
+        let droppable: Vec<usize> = messages
+            .iter()
+            .enumerate()
+            .take(messages.len().saturating_sub(1))
+            .filter(|(_, message)| message.role != Role::System.as_str())
+            .map(|(index, _)| index)
+            .collect();
+        match self {
+            TruncationStrategy::DropOldest => droppable.first().copied(),
+            TruncationStrategy::DropMiddle => {
+                let middle = droppable.get(1..)?;
+                middle.get(middle.len() / 2).copied()
+            }
+            TruncationStrategy::None | TruncationStrategy::Error => None,
+        }
+    }
+}
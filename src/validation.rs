@@ -0,0 +1,47 @@
+use std::{fmt, ops::RangeInclusive};
+
+use crate::Error;
+
+/// Range of `frequency_penalty` and `presence_penalty` accepted by the API.
+pub(crate) const PENALTY_RANGE: RangeInclusive<f64> = -2.0..=2.0;
+
+/// A parameter combination which is accepted by the API, but likely not what you intended. Returned
+/// by the `validate` methods of tasks, e.g. [`crate::TaskCompletion::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationWarning {
+    /// Name of the parameter, as send to the API. E.g. `maximum_tokens`.
+    pub field: String,
+    /// Explains why the value is suspicious.
+    pub message: String,
+}
+
+impl ValidationWarning {
+    pub(crate) fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Fails with [`Error::InvalidParameter`] if the penalty sent as `field` is outside of
+/// [`PENALTY_RANGE`].
+pub(crate) fn check_penalty(field: &str, penalty: Option<f64>) -> Result<(), Error> {
+    match penalty {
+        Some(penalty) if !PENALTY_RANGE.contains(&penalty) => Err(Error::InvalidParameter {
+            field: field.to_owned(),
+            message: format!(
+                "{penalty} is outside of the accepted range [{}, {}]",
+                PENALTY_RANGE.start(),
+                PENALTY_RANGE.end()
+            ),
+        }),
+        _ => Ok(()),
+    }
+}
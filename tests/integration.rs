@@ -168,6 +168,7 @@ async fn semantic_search_with_luminous_base() {
         prompt: robot_fact,
         representation: SemanticRepresentation::Document,
         compress_to_size: Some(128),
+        normalize: false,
     };
     let robot_embedding = client
         .semantic_embedding(&robot_embedding_task, &How::default())
@@ -179,6 +180,7 @@ async fn semantic_search_with_luminous_base() {
         prompt: pizza_fact,
         representation: SemanticRepresentation::Document,
         compress_to_size: Some(128),
+        normalize: false,
     };
     let pizza_embedding = client
         .semantic_embedding(&pizza_embedding_task, &How::default())
@@ -190,6 +192,7 @@ async fn semantic_search_with_luminous_base() {
         prompt: query,
         representation: SemanticRepresentation::Query,
         compress_to_size: Some(128),
+        normalize: false,
     };
     let query_embedding = client
         .semantic_embedding(&query_embedding_task, &How::default())
@@ -220,10 +223,16 @@ async fn complete_structured_prompt() {
         stopping: Stopping {
             maximum_tokens: Some(64),
             stop_sequences: &stop_sequences[..],
+            include_stop_in_output: false,
         },
         sampling: Sampling::MOST_LIKELY,
         special_tokens: false,
         logprobs: Logprobs::No,
+        disable_optimizations: None,
+        guided_json: None,
+        tokens: false,
+        extra: serde_json::Map::new(),
+        priority: None,
     };
     let model = "luminous-base";
     let client = Client::with_auth(inference_url(), pharia_ai_token()).unwrap();
@@ -245,6 +254,7 @@ async fn maximum_tokens_none_request() {
     let var_name = Stopping {
         maximum_tokens: None,
         stop_sequences: &["User"],
+        include_stop_in_output: false,
     };
     let stopping = var_name;
 
@@ -255,6 +265,11 @@ async fn maximum_tokens_none_request() {
         sampling: Sampling::MOST_LIKELY,
         special_tokens: false,
         logprobs: Logprobs::No,
+        disable_optimizations: None,
+        guided_json: None,
+        tokens: false,
+        extra: serde_json::Map::new(),
+        priority: None,
     };
     let model = "luminous-base";
     let client = Client::with_auth(inference_url(), pharia_ai_token()).unwrap();
@@ -390,6 +405,11 @@ async fn describe_image_starting_from_a_path() {
         sampling: Sampling::MOST_LIKELY,
         special_tokens: false,
         logprobs: Logprobs::No,
+        disable_optimizations: None,
+        guided_json: None,
+        tokens: false,
+        extra: serde_json::Map::new(),
+        priority: None,
     };
     let model = "luminous-base";
     let client = Client::with_auth(inference_url(), pharia_ai_token()).unwrap();
@@ -421,6 +441,11 @@ async fn describe_image_starting_from_a_dyn_image() {
         sampling: Sampling::MOST_LIKELY,
         special_tokens: false,
         logprobs: Logprobs::No,
+        disable_optimizations: None,
+        guided_json: None,
+        tokens: false,
+        extra: serde_json::Map::new(),
+        priority: None,
     };
     let model = "luminous-base";
     let client = Client::with_auth(inference_url(), pharia_ai_token()).unwrap();
@@ -456,6 +481,7 @@ async fn batch_semanitc_embed_with_luminous_base() {
         prompts: vec![robot_fact, pizza_fact],
         representation: SemanticRepresentation::Document,
         compress_to_size: Some(128),
+        normalize: false,
     };
 
     let embeddings = client
@@ -469,6 +495,29 @@ async fn batch_semanitc_embed_with_luminous_base() {
     assert_eq!(embeddings.len(), 2);
 }
 
+#[tokio::test]
+async fn normalized_semantic_embedding_has_unit_length() {
+    // Given
+    let client = Client::with_auth(inference_url(), pharia_ai_token()).unwrap();
+    let task = TaskSemanticEmbedding {
+        prompt: Prompt::from_text("An apple a day keeps the doctor away."),
+        representation: SemanticRepresentation::Symmetric,
+        compress_to_size: Some(128),
+        normalize: true,
+    };
+
+    // When
+    let embedding = client
+        .semantic_embedding(&task, &How::default())
+        .await
+        .unwrap()
+        .embedding;
+
+    // Then
+    let length = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    assert!((length - 1.0).abs() < 1e-3);
+}
+
 #[tokio::test]
 async fn tokenization_with_luminous_base() {
     // Given
@@ -603,7 +652,7 @@ async fn stream_chat_with_pharia_1_llm_7b() {
         }))
     ));
     assert!(
-        matches!(&events[2], Ok(StreamChatEvent::Chunk(ChatChunk::Finished { reason })) if reason == "stop")
+        matches!(&events[2], Ok(StreamChatEvent::Chunk(ChatChunk::Finished { reason, .. })) if reason == "stop")
     );
     assert!(matches!(&events[3], Ok(StreamChatEvent::Usage(_))));
 }
@@ -624,6 +673,12 @@ async fn frequency_penalty_request() {
         stopping,
         sampling,
         logprobs: Logprobs::No,
+        guided_json: None,
+        documents: Vec::new(),
+        extra: serde_json::Map::new(),
+        tokens: false,
+        priority: None,
+        continue_final_message: false,
     };
 
     // When the response is requested
@@ -660,6 +715,12 @@ async fn presence_penalty_request() {
         stopping,
         sampling,
         logprobs: Logprobs::No,
+        guided_json: None,
+        documents: Vec::new(),
+        extra: serde_json::Map::new(),
+        tokens: false,
+        priority: None,
+        continue_final_message: false,
     };
 
     // When the response is requested
@@ -690,12 +751,19 @@ async fn stop_sequences_request() {
     let stopping = Stopping {
         stop_sequences: &["doctor"],
         maximum_tokens: None,
+        include_stop_in_output: false,
     };
     let task = TaskChat {
         messages: vec![message],
         stopping,
         sampling: ChatSampling::MOST_LIKELY,
         logprobs: Logprobs::No,
+        guided_json: None,
+        documents: Vec::new(),
+        extra: serde_json::Map::new(),
+        tokens: false,
+        priority: None,
+        continue_final_message: false,
     };
 
     // When the response is requested
@@ -722,6 +790,12 @@ async fn show_logprobs_sampled_chat() {
         stopping: Stopping::from_maximum_tokens(2),
         sampling: ChatSampling::MOST_LIKELY,
         logprobs: Logprobs::Sampled,
+        guided_json: None,
+        documents: Vec::new(),
+        extra: serde_json::Map::new(),
+        tokens: false,
+        priority: None,
+        continue_final_message: false,
     };
 
     let response = client.chat(&task, model, &How::default()).await.unwrap();
@@ -748,6 +822,12 @@ async fn show_top_logprobs_chat() {
         stopping: Stopping::from_maximum_tokens(1),
         sampling: ChatSampling::MOST_LIKELY,
         logprobs: Logprobs::Top(2),
+        guided_json: None,
+        documents: Vec::new(),
+        extra: serde_json::Map::new(),
+        tokens: false,
+        priority: None,
+        continue_final_message: false,
     };
 
     let response = client.chat(&task, model, &How::default()).await.unwrap();
@@ -837,6 +917,12 @@ async fn show_token_usage_chat() {
         stopping: Stopping::from_maximum_tokens(3),
         sampling: ChatSampling::MOST_LIKELY,
         logprobs: Logprobs::No,
+        guided_json: None,
+        documents: Vec::new(),
+        extra: serde_json::Map::new(),
+        tokens: false,
+        priority: None,
+        continue_final_message: false,
     };
 
     // When
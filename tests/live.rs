@@ -0,0 +1,84 @@
+//! Checks invariants of real responses of the production API, to catch schema drift which the
+//! mocks in `unit.rs` can not detect. Run with `cargo test --features integration-tests`. Each test
+//! is skipped if the `AA_API_TOKEN` environment variable is not set, so CI without secrets still
+//! passes.
+#![cfg(feature = "integration-tests")]
+
+use aleph_alpha_client::{
+    Client, How, Message, Prompt, SemanticRepresentation, TaskChat, TaskCompletion,
+    TaskSemanticEmbedding,
+};
+use dotenvy::dotenv;
+
+/// Base URL of the production API. May be overwritten with `AA_API_URL`.
+const PRODUCTION_URL: &str = "https://api.aleph-alpha.com";
+
+/// Client authenticated with `AA_API_TOKEN`, or `None` if the token is not set.
+fn live_client() -> Option<Client> {
+    drop(dotenv());
+    let Ok(api_token) = std::env::var("AA_API_TOKEN") else {
+        eprintln!("AA_API_TOKEN is not set. Skipping test against the live API.");
+        return None;
+    };
+    let url = std::env::var("AA_API_URL").unwrap_or_else(|_| PRODUCTION_URL.to_owned());
+    Some(Client::with_auth(url, api_token).unwrap())
+}
+
+#[tokio::test]
+async fn live_completion() {
+    // Given
+    let Some(client) = live_client() else { return };
+    let task = TaskCompletion::from_text("An apple a day").with_maximum_tokens(5);
+
+    // When
+    let response = client
+        .completion(&task, "luminous-base", &How::default())
+        .await
+        .unwrap();
+
+    // Then
+    assert!(!response.completion.is_empty());
+    assert!(!response.finish_reason.is_empty());
+    assert!(response.usage.prompt_tokens > 0);
+    assert!(response.usage.completion_tokens > 0);
+}
+
+#[tokio::test]
+async fn live_chat() {
+    // Given
+    let Some(client) = live_client() else { return };
+    let task = TaskChat::with_message(Message::user("Hello!")).with_maximum_tokens(10);
+
+    // When
+    let response = client
+        .chat(&task, "pharia-1-llm-7b-control", &How::default())
+        .await
+        .unwrap();
+
+    // Then
+    assert!(!response.message.content.is_empty());
+    assert_eq!(response.message.role, "assistant");
+    assert!(response.usage.prompt_tokens > 0);
+    assert!(response.usage.completion_tokens > 0);
+}
+
+#[tokio::test]
+async fn live_semantic_embedding() {
+    // Given
+    let Some(client) = live_client() else { return };
+    let task = TaskSemanticEmbedding {
+        prompt: Prompt::from_text("An apple a day keeps the doctor away."),
+        representation: SemanticRepresentation::Symmetric,
+        compress_to_size: Some(128),
+        normalize: false,
+    };
+
+    // When
+    let response = client
+        .semantic_embedding(&task, &How::default())
+        .await
+        .unwrap();
+
+    // Then
+    assert_eq!(response.embedding.len(), 128);
+}